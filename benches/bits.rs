@@ -0,0 +1,69 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use cryptopals::bits;
+
+// The bit by bit versions that set1 used before the bits module, including the bug that skipped
+// the high bit.
+fn differing_bits(a: u8, b: u8) -> usize {
+    (0..7)
+        .map(|x| (1 << x) & (a ^ b))
+        .filter(|x| *x != 0)
+        .count()
+}
+
+fn bytewise_hamming_distance(s1: &[u8], s2: &[u8]) -> usize {
+    s1.iter()
+        .zip(s2.iter())
+        .map(|(a, b)| differing_bits(*a, *b))
+        .sum()
+}
+
+fn bytewise_xor(l: &[u8], r: &[u8]) -> Vec<u8> {
+    l.iter().zip(r.iter()).map(|(l, r)| l ^ r).collect()
+}
+
+fn time<T>(name: &str, iterations: u32, f: impl Fn() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(f());
+    }
+    let per_iteration = start.elapsed() / iterations;
+    println!("{:<28} {:>12?}", name, per_iteration);
+    per_iteration
+}
+
+fn main() {
+    let l: Vec<u8> = (0..1 << 20).map(|i: u32| (i * 31 + 7) as u8).collect();
+    let r: Vec<u8> = (0..1 << 20).map(|i: u32| (i * 17 + 3) as u8).collect();
+    let low_bits = |data: &[u8]| data.iter().map(|b| b & 0x7f).collect::<Vec<_>>();
+    assert_eq!(
+        bytewise_hamming_distance(&l, &r),
+        bits::hamming_distance(&low_bits(&l), &low_bits(&r))
+    );
+    assert_eq!(bytewise_xor(&l, &r), bits::xor(&l, &r));
+
+    println!("1 MiB inputs");
+    let old = time("hamming distance, bytewise", 20, || {
+        bytewise_hamming_distance(black_box(&l), black_box(&r))
+    });
+    let new = time("hamming distance, popcount", 20, || {
+        bits::hamming_distance(black_box(&l), black_box(&r))
+    });
+    println!(
+        "{:<28} {:>11.1}x",
+        "speedup",
+        old.as_secs_f64() / new.as_secs_f64()
+    );
+    let old = time("xor, bytewise", 20, || {
+        bytewise_xor(black_box(&l), black_box(&r))
+    });
+    let new = time("xor, word at a time", 20, || {
+        bits::xor(black_box(&l), black_box(&r))
+    });
+    println!(
+        "{:<28} {:>11.1}x",
+        "speedup",
+        old.as_secs_f64() / new.as_secs_f64()
+    );
+}
@@ -0,0 +1,102 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use cryptopals::crib_drag::CribDrag;
+use cryptopals::data::Ciphertext;
+use cryptopals::language_model::LanguageModel;
+use structopt::StructOpt;
+
+/// Breaks ciphertexts that were XOR'ed with the same keystream
+#[derive(StructOpt)]
+struct Options {
+    /// File with one ciphertext per line, hex encoded unless --base64 is given
+    ciphertext_file: std::path::PathBuf,
+    #[structopt(long)]
+    base64: bool,
+    /// Slide this text across every pair of ciphertexts and show the best fragments
+    #[structopt(short, long)]
+    crib: Option<String>,
+    /// How many fragments to show for the crib
+    #[structopt(short = "n", long, default_value = "20")]
+    matches: usize,
+    /// Plaintext guesses as message:offset:text, applied in order
+    #[structopt(short, long)]
+    guess: Vec<Guess>,
+    /// Score the fragments with a model from train-language-model instead of the built in English
+    #[structopt(short, long)]
+    model: Option<std::path::PathBuf>,
+}
+
+struct Guess {
+    message: usize,
+    offset: usize,
+    text: String,
+}
+
+impl FromStr for Guess {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.splitn(3, ':');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(message), Some(offset), Some(text)) => Ok(Guess {
+                message: message.parse()?,
+                offset: offset.parse()?,
+                text: text.to_string(),
+            }),
+            _ => Err(anyhow!("Expected message:offset:text, got {}", s)),
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let options = Options::from_args();
+    let input = std::fs::read_to_string(&options.ciphertext_file)?;
+    let ciphertexts = input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            if options.base64 {
+                Ciphertext::from_base64(line)
+            } else {
+                Ciphertext::from_hex(line)
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut crib_drag = CribDrag::new(ciphertexts)?;
+    for guess in &options.guess {
+        crib_drag.guess(guess.message, guess.offset, guess.text.as_bytes())?;
+    }
+
+    if let Some(crib) = &options.crib {
+        let loaded;
+        let model = match &options.model {
+            Some(path) => {
+                loaded = LanguageModel::load(path)
+                    .with_context(|| format!("Can't load {}", path.display()))?;
+                &loaded
+            }
+            None => LanguageModel::english(),
+        };
+        for m in crib_drag
+            .drag(crib.as_bytes(), model)
+            .iter()
+            .take(options.matches)
+        {
+            println!(
+                "{:>3} {:>3} @ {:<4} {:8.3} {:?}",
+                m.pair.0,
+                m.pair.1,
+                m.offset,
+                m.score,
+                String::from_utf8_lossy(&m.fragment)
+            );
+        }
+        println!();
+    }
+
+    for (index, plaintext) in crib_drag.render().iter().enumerate() {
+        println!("{:>3}: {}", index, plaintext);
+    }
+    Ok(())
+}
@@ -0,0 +1,388 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use aes::{Aes128, Aes192, Aes256};
+use anyhow::{bail, Context, Result};
+use cryptopals::cli::{
+    create_output, is_std_stream, open_input, read_encoded, read_input, write_output,
+};
+use cryptopals::data::{AesKey, Encoding, Iv};
+use cryptopals::error::CryptoError;
+use cryptopals::modes::{decrypt_stream, encrypt_stream, BlockMode};
+use cryptopals::recipe::Recipe;
+use cryptopals::set1::challenge1::{hex_to_base64, hex_to_base64_homebrew};
+use cryptopals::set1::challenge2::fixed_xor;
+use cryptopals::set1::challenge8::{scan_for_ecb, EcbFinding, EcbScanOptions};
+use cryptopals::set2::challenge9::Padding;
+use structopt::StructOpt;
+
+/// The cryptopals solutions as a command line tool. Use - for stdin or stdout.
+#[derive(StructOpt)]
+enum Command {
+    /// Converts between raw, hex and base64
+    Convert(Convert),
+    /// Converts hex to base64 with the challenge 1 solution
+    HexToBase64(HexToBase64),
+    /// XORs two inputs of the same length
+    FixedXor(FixedXor),
+    /// Encrypts with AES-128, AES-192 or AES-256, depending on the key length
+    AesEncrypt(Aes),
+    /// Decrypts with AES-128, AES-192 or AES-256, depending on the key length
+    AesDecrypt(Aes),
+    /// Looks for repeated blocks, the tell of ECB, at every alignment
+    DetectEcb(DetectEcb),
+    /// Runs a pipeline of operations, such as from-base64 | aes-ecb-decrypt key="..."
+    Recipe(RecipeCommand),
+}
+
+#[derive(StructOpt)]
+struct Convert {
+    /// raw, hex or base64
+    #[structopt(short, long, default_value = "raw")]
+    input_encoding: Encoding,
+    /// raw, hex or base64
+    #[structopt(short, long, default_value = "raw")]
+    output_encoding: Encoding,
+    #[structopt(long, default_value = "-")]
+    output_file: PathBuf,
+    #[structopt(default_value = "-")]
+    input_file: PathBuf,
+}
+
+#[derive(StructOpt)]
+struct HexToBase64 {
+    /// Use the homebrew base64 encoder
+    #[structopt(long)]
+    homebrew: bool,
+    #[structopt(default_value = "-")]
+    input_file: PathBuf,
+}
+
+#[derive(StructOpt)]
+struct FixedXor {
+    /// raw, hex or base64
+    #[structopt(short, long, default_value = "hex")]
+    input_encoding: Encoding,
+    /// raw, hex or base64
+    #[structopt(short, long, default_value = "hex")]
+    output_encoding: Encoding,
+    #[structopt(long, default_value = "-")]
+    output_file: PathBuf,
+    left_file: PathBuf,
+    right_file: PathBuf,
+}
+
+#[derive(StructOpt)]
+struct Aes {
+    /// ecb or cbc
+    #[structopt(short, long, default_value = "cbc")]
+    mode: AesMode,
+    /// The key as text
+    #[structopt(short, long)]
+    key: Option<String>,
+    /// The key as hex
+    #[structopt(long)]
+    key_hex: Option<String>,
+    /// The IV as hex, required for CBC
+    #[structopt(long)]
+    iv: Option<String>,
+    /// pkcs7 or none
+    #[structopt(short, long, default_value = "pkcs7")]
+    padding: Padding,
+    #[structopt(long, default_value = "-")]
+    output_file: PathBuf,
+    #[structopt(default_value = "-")]
+    input_file: PathBuf,
+}
+
+#[derive(StructOpt)]
+struct DetectEcb {
+    /// Treat every line of the inputs as a hex encoded input of its own
+    #[structopt(long)]
+    hex_lines: bool,
+    /// The block sizes to try, 8 and 16 if none are given
+    #[structopt(short, long, number_of_values = 1)]
+    block_size: Vec<usize>,
+    /// Also count blocks of a single repeated byte, such as zero padding
+    #[structopt(long)]
+    keep_uniform_blocks: bool,
+    /// How many of the most ECB like inputs to show
+    #[structopt(short = "n", long, default_value = "10")]
+    top: usize,
+    /// How many alignments to show for each input
+    #[structopt(long, default_value = "1")]
+    findings: usize,
+    /// How many repeated blocks to show for each alignment
+    #[structopt(long, default_value = "5")]
+    blocks: usize,
+    #[structopt(default_value = "-")]
+    input_files: Vec<PathBuf>,
+}
+
+#[derive(StructOpt)]
+struct RecipeCommand {
+    /// The recipe itself
+    #[structopt(short = "e", long)]
+    recipe: Option<String>,
+    /// Read the recipe from a file
+    #[structopt(short = "f", long)]
+    recipe_file: Option<PathBuf>,
+    #[structopt(long, default_value = "-")]
+    output_file: PathBuf,
+    #[structopt(default_value = "-")]
+    input_file: PathBuf,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum AesMode {
+    Ecb,
+    Cbc,
+}
+
+impl std::str::FromStr for AesMode {
+    type Err = CryptoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ecb" => Ok(AesMode::Ecb),
+            "cbc" => Ok(AesMode::Cbc),
+            _ => Err(CryptoError::InvalidParameter(
+                "Unknown mode, expected ecb or cbc",
+            )),
+        }
+    }
+}
+
+impl Convert {
+    fn run(self) -> Result<()> {
+        let data = read_encoded(&self.input_file, self.input_encoding)?;
+        write_output(&self.output_file, &data, self.output_encoding)?;
+        Ok(())
+    }
+}
+
+impl HexToBase64 {
+    fn run(self) -> Result<()> {
+        let input = String::from_utf8(read_input(&self.input_file)?)?;
+        let hex = input.trim();
+        let base64 = if self.homebrew {
+            hex_to_base64_homebrew(hex)?
+        } else {
+            hex_to_base64(hex)?
+        };
+        println!("{}", base64);
+        Ok(())
+    }
+}
+
+impl FixedXor {
+    fn run(self) -> Result<()> {
+        if is_std_stream(&self.left_file) && is_std_stream(&self.right_file) {
+            bail!("Only one of the inputs can be read from stdin");
+        }
+        let left = read_encoded(&self.left_file, self.input_encoding)?;
+        let right = read_encoded(&self.right_file, self.input_encoding)?;
+        let result = fixed_xor(&left, &right)?;
+        write_output(&self.output_file, &result, self.output_encoding)?;
+        Ok(())
+    }
+}
+
+impl Aes {
+    fn run(self, encrypt: bool) -> Result<()> {
+        let key = match (&self.key, &self.key_hex) {
+            (Some(text), None) => AesKey::new(text.as_bytes()),
+            (None, Some(hex)) => AesKey::new(&hex::decode(hex.trim()).map_err(CryptoError::from)?),
+            _ => bail!("Give exactly one of --key and --key-hex"),
+        }
+        .context("Bad key")?;
+        let iv = match (self.mode, &self.iv) {
+            (AesMode::Cbc, Some(hex)) => Some(
+                Iv::block(&hex::decode(hex.trim()).map_err(CryptoError::from)?)
+                    .context("Bad IV")?,
+            ),
+            (AesMode::Cbc, None) => bail!("CBC needs an IV, give one with --iv"),
+            (AesMode::Ecb, Some(_)) => bail!("ECB doesn't use an IV, leave out --iv"),
+            (AesMode::Ecb, None) => None,
+        };
+        let mode = match &iv {
+            Some(iv) => BlockMode::Cbc(iv),
+            None => BlockMode::Ecb,
+        };
+
+        let input = open_input(&self.input_file)
+            .with_context(|| format!("Can't open {}", self.input_file.display()))?;
+        let result = if is_std_stream(&self.output_file) {
+            self.process(
+                encrypt,
+                &key,
+                mode,
+                input,
+                create_output(&self.output_file)?,
+            )
+        } else {
+            // Write next to the output file and rename it into place when done, so a failed
+            // run never leaves a half written file behind or clobbers an existing one.
+            let mut name = match self.output_file.file_name() {
+                Some(name) => name.to_os_string(),
+                None => bail!("{} is not a file name", self.output_file.display()),
+            };
+            name.push(format!(".{}.tmp", std::process::id()));
+            let temp_file = TempFile::new(self.output_file.with_file_name(name));
+            let output = create_output(&temp_file.path)
+                .with_context(|| format!("Can't create {}", temp_file.path.display()))?;
+            let result = self.process(encrypt, &key, mode, input, output);
+            if result.is_ok() {
+                temp_file
+                    .rename(&self.output_file)
+                    .with_context(|| format!("Can't write {}", self.output_file.display()))?;
+            }
+            result
+        };
+        match result {
+            Err(e @ CryptoError::Padding(_)) => {
+                bail!("{}. Is the key, IV or padding wrong?", e)
+            }
+            Err(e) => Err(e.into()),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    fn process(
+        &self,
+        encrypt: bool,
+        key: &AesKey,
+        mode: BlockMode,
+        input: impl Read,
+        output: impl Write,
+    ) -> Result<u64, CryptoError> {
+        match (key.len(), encrypt) {
+            (16, true) => encrypt_stream::<Aes128>(input, output, key, mode, self.padding),
+            (16, false) => decrypt_stream::<Aes128>(input, output, key, mode, self.padding),
+            (24, true) => encrypt_stream::<Aes192>(input, output, key, mode, self.padding),
+            (24, false) => decrypt_stream::<Aes192>(input, output, key, mode, self.padding),
+            (32, true) => encrypt_stream::<Aes256>(input, output, key, mode, self.padding),
+            (32, false) => decrypt_stream::<Aes256>(input, output, key, mode, self.padding),
+            (actual, _) => Err(CryptoError::InvalidKeyLength {
+                expected: &[16, 24, 32],
+                actual,
+            }),
+        }
+    }
+}
+
+/// A file that is removed when dropped, unless it has been renamed into place.
+struct TempFile {
+    path: PathBuf,
+    renamed: bool,
+}
+
+impl TempFile {
+    fn new(path: PathBuf) -> TempFile {
+        TempFile {
+            path,
+            renamed: false,
+        }
+    }
+
+    fn rename(mut self, to: &Path) -> std::io::Result<()> {
+        std::fs::rename(&self.path, to)?;
+        self.renamed = true;
+        Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.renamed {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl DetectEcb {
+    fn run(self) -> Result<()> {
+        let mut options = EcbScanOptions {
+            skip_uniform_blocks: !self.keep_uniform_blocks,
+            ..EcbScanOptions::default()
+        };
+        if !self.block_size.is_empty() {
+            options.block_sizes = &self.block_size;
+        }
+        let mut results: Vec<(String, Vec<EcbFinding>)> = vec![];
+        for path in &self.input_files {
+            let data = read_input(path)?;
+            if self.hex_lines {
+                for (index, line) in data.split(|b| *b == b'\n').enumerate() {
+                    let line = line.trim_ascii();
+                    if line.is_empty() {
+                        continue;
+                    }
+                    let name = format!("{}:{}", path.display(), index + 1);
+                    let data = hex::decode(line).with_context(|| format!("Bad hex in {}", name))?;
+                    results.push((name, scan_for_ecb(&data, &options)?));
+                }
+            } else {
+                let name = path.display().to_string();
+                results.push((name, scan_for_ecb(&data, &options)?));
+            }
+        }
+
+        results.retain(|(_, findings)| !findings.is_empty());
+        results.sort_by(|(_, l), (_, r)| r[0].score.total_cmp(&l[0].score));
+        if results.is_empty() {
+            println!("No repeated blocks found");
+        }
+        for (name, findings) in results.iter().take(self.top) {
+            println!("{}: score {:.3}", name, findings[0].score);
+            for finding in findings.iter().take(self.findings) {
+                let region = finding.region();
+                println!(
+                    "  block size {}, alignment {}, {} repeated blocks in bytes {}..{}",
+                    finding.block_size,
+                    finding.alignment,
+                    finding.repeated.len(),
+                    region.start,
+                    region.end
+                );
+                for repeated in finding.repeated.iter().take(self.blocks) {
+                    let offsets: Vec<String> =
+                        repeated.offsets.iter().map(|o| o.to_string()).collect();
+                    println!(
+                        "    {} at {}",
+                        hex::encode(&repeated.block),
+                        offsets.join(", ")
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RecipeCommand {
+    fn run(self) -> Result<()> {
+        let text = match (&self.recipe, &self.recipe_file) {
+            (Some(text), None) => text.clone(),
+            (None, Some(path)) => std::fs::read_to_string(path)
+                .with_context(|| format!("Can't read {}", path.display()))?,
+            _ => bail!("Give exactly one of --recipe and --recipe-file"),
+        };
+        let recipe: Recipe = text.parse()?;
+        let output = recipe.run(read_input(&self.input_file)?)?;
+        write_output(&self.output_file, &output, Encoding::Raw)?;
+        Ok(())
+    }
+}
+
+fn main() -> Result<()> {
+    match Command::from_args() {
+        Command::Convert(command) => command.run(),
+        Command::HexToBase64(command) => command.run(),
+        Command::FixedXor(command) => command.run(),
+        Command::AesEncrypt(command) => command.run(true),
+        Command::AesDecrypt(command) => command.run(false),
+        Command::DetectEcb(command) => command.run(),
+        Command::Recipe(command) => command.run(),
+    }
+}
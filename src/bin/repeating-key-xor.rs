@@ -1,23 +1,128 @@
-use std::str::FromStr;
+use std::path::PathBuf;
 
-use anyhow::Result;
-use cryptopals::data::{Key, Plaintext};
-use cryptopals::set1::challenge5::repeating_key_xor_encrypt;
+use anyhow::{bail, Result};
+use cryptopals::cli::{read_encoded, read_input, write_output};
+use cryptopals::data::{Ciphertext, Encoding, Key, Plaintext};
+use cryptopals::set1::challenge5::{repeating_key_xor_decrypt, repeating_key_xor_encrypt};
+use cryptopals::set1::challenge6::{rank_repeating_key_xor, RepeatingKeyXorOptions};
 use structopt::StructOpt;
 
+/// Encrypts, decrypts and breaks repeating key XOR. Use - for stdin or stdout.
 #[derive(StructOpt)]
-struct Options {
-    plain_text_file: std::path::PathBuf,
-    cipher_text_file: std::path::PathBuf,
-    key: String,
+enum Options {
+    Encrypt(Crypt),
+    Decrypt(Crypt),
+    /// Recovers the key of a ciphertext
+    Break(Break),
+}
+
+#[derive(StructOpt)]
+struct Crypt {
+    #[structopt(flatten)]
+    key: KeyOptions,
+    /// raw, hex or base64
+    #[structopt(short, long, default_value = "raw")]
+    input_encoding: Encoding,
+    /// raw, hex or base64
+    #[structopt(short = "e", long, default_value = "raw")]
+    output_encoding: Encoding,
+    #[structopt(short, long, default_value = "-")]
+    output_file: PathBuf,
+    input_file: PathBuf,
+}
+
+#[derive(StructOpt)]
+struct KeyOptions {
+    /// The key as text
+    #[structopt(short, long)]
+    key: Option<String>,
+    /// The key as hex
+    #[structopt(long)]
+    key_hex: Option<String>,
+    /// Read the key from a file, as raw bytes
+    #[structopt(long)]
+    key_file: Option<PathBuf>,
+}
+
+#[derive(StructOpt)]
+struct Break {
+    /// raw, hex or base64
+    #[structopt(short, long, default_value = "raw")]
+    input_encoding: Encoding,
+    #[structopt(long, default_value = "2")]
+    min_key_size: usize,
+    #[structopt(long, default_value = "60")]
+    max_key_size: usize,
+    /// How many of the best keys to show
+    #[structopt(short = "n", long, default_value = "1")]
+    candidates: usize,
+    /// How much of each plaintext to show
+    #[structopt(long, default_value = "200")]
+    preview: usize,
+    /// Write the plaintext of the best key here
+    #[structopt(short, long)]
+    output_file: Option<PathBuf>,
+    input_file: PathBuf,
+}
+
+impl KeyOptions {
+    fn key(&self) -> Result<Key> {
+        let key = match (&self.key, &self.key_hex, &self.key_file) {
+            (Some(text), None, None) => Key::from(text.as_bytes().to_vec()),
+            (None, Some(hex), None) => Key::from_hex(hex)?,
+            (None, None, Some(path)) => Key::from(read_input(path)?),
+            _ => bail!("Give exactly one of --key, --key-hex and --key-file"),
+        };
+        Ok(key)
+    }
+}
+
+fn preview(data: &[u8], len: usize) -> String {
+    let mut preview: String = String::from_utf8_lossy(&data[..data.len().min(len)])
+        .chars()
+        .flat_map(char::escape_default)
+        .collect();
+    if data.len() > len {
+        preview.push_str("...");
+    }
+    preview
 }
 
 fn main() -> Result<()> {
-    let options = Options::from_args();
-    let input = std::fs::read_to_string(options.plain_text_file)?;
-    let plaintext = Plaintext::from_str(&input)?;
-    let key = Key::from_str(&options.key)?;
-    let cipher_text = repeating_key_xor_encrypt(&plaintext, &key);
-    std::fs::write(options.cipher_text_file, cipher_text.0)?;
+    match Options::from_args() {
+        Options::Encrypt(options) => {
+            let plaintext = Plaintext(read_encoded(&options.input_file, options.input_encoding)?);
+            let ciphertext = repeating_key_xor_encrypt(&plaintext, &options.key.key()?)?;
+            write_output(&options.output_file, &ciphertext.0, options.output_encoding)?;
+        }
+        Options::Decrypt(options) => {
+            let ciphertext = Ciphertext(read_encoded(&options.input_file, options.input_encoding)?);
+            let plaintext = repeating_key_xor_decrypt(&ciphertext, &options.key.key()?)?;
+            write_output(&options.output_file, &plaintext.0, options.output_encoding)?;
+        }
+        Options::Break(options) => {
+            let ciphertext = Ciphertext(read_encoded(&options.input_file, options.input_encoding)?);
+            let break_options = RepeatingKeyXorOptions {
+                min_key_size: options.min_key_size,
+                max_key_size: options.max_key_size,
+                ..RepeatingKeyXorOptions::default()
+            };
+            let candidates = rank_repeating_key_xor(&ciphertext, &break_options)?;
+            for candidate in candidates.iter().take(options.candidates) {
+                println!(
+                    "Key ({} bytes): {}",
+                    candidate.key.len(),
+                    preview(candidate.key.as_bytes(), candidate.key.len())
+                );
+                println!("Key as hex: {}", candidate.key.to_hex());
+                println!("Score: {:.3}", candidate.score);
+                println!("{}", preview(&candidate.plaintext.0, options.preview));
+                println!();
+            }
+            if let Some(path) = &options.output_file {
+                write_output(path, &candidates[0].plaintext.0, Encoding::Raw)?;
+            }
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,125 @@
+use anyhow::{bail, Result};
+use cryptopals::challenges::{challenge, challenges_in_set, Challenge, Outcome, CHALLENGES};
+use structopt::StructOpt;
+
+/// Solves the cryptopals challenges and checks the answers. Runs all of them by default.
+#[derive(StructOpt)]
+struct Options {
+    /// Run only the challenges in this set
+    #[structopt(short, long, number_of_values = 1)]
+    set: Vec<u32>,
+    /// Print the results as JSON, for tracking regressions
+    #[structopt(long)]
+    json: bool,
+    /// The challenges to run
+    challenges: Vec<u32>,
+}
+
+fn selected(options: &Options) -> Result<Vec<&'static Challenge>> {
+    if options.set.is_empty() && options.challenges.is_empty() {
+        return Ok(CHALLENGES.iter().collect());
+    }
+    let mut selected = vec![];
+    for set in &options.set {
+        let before = selected.len();
+        selected.extend(challenges_in_set(*set));
+        if selected.len() == before {
+            bail!("There are no challenges in set {}", set);
+        }
+    }
+    for number in &options.challenges {
+        match challenge(*number) {
+            Some(challenge) => selected.push(challenge),
+            None => bail!("There is no challenge {}", number),
+        }
+    }
+    selected.sort_by_key(|c| c.number);
+    selected.dedup_by_key(|c| c.number);
+    Ok(selected)
+}
+
+fn print_text(results: &[(&Challenge, Outcome)]) {
+    for (challenge, outcome) in results {
+        println!(
+            "{:>2}. {:<32} {:<4} {:>10.3} ms",
+            challenge.number,
+            challenge.title,
+            if outcome.passed(challenge) {
+                "ok"
+            } else {
+                "FAIL"
+            },
+            outcome.duration.as_secs_f64() * 1000.0
+        );
+        match &outcome.answer {
+            Ok(answer) if outcome.passed(challenge) => println!("    {}", answer),
+            Ok(answer) => println!("    got {}\n    expected {}", answer, challenge.expected),
+            Err(e) => println!("    error: {}\n    expected {}", e, challenge.expected),
+        }
+    }
+    let passed = results.iter().filter(|(c, o)| o.passed(c)).count();
+    println!("\n{} passed, {} failed", passed, results.len() - passed);
+}
+
+fn print_json(results: &[(&Challenge, Outcome)]) {
+    let entries: Vec<String> = results
+        .iter()
+        .map(|(challenge, outcome)| {
+            let (answer, error) = match &outcome.answer {
+                Ok(answer) => (json_string(answer), "null".to_string()),
+                Err(e) => ("null".to_string(), json_string(&e.to_string())),
+            };
+            format!(
+                "{{\"set\": {}, \"number\": {}, \"title\": {}, \"passed\": {}, \"answer\": {}, \
+                 \"error\": {}, \"expected\": {}, \"seconds\": {}}}",
+                challenge.set,
+                challenge.number,
+                json_string(challenge.title),
+                outcome.passed(challenge),
+                answer,
+                error,
+                json_string(challenge.expected),
+                outcome.duration.as_secs_f64()
+            )
+        })
+        .collect();
+    let passed = results.iter().filter(|(c, o)| o.passed(c)).count();
+    println!(
+        "{{\"passed\": {}, \"failed\": {}, \"challenges\": [\n  {}\n]}}",
+        passed,
+        results.len() - passed,
+        entries.join(",\n  ")
+    );
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn main() -> Result<()> {
+    let options = Options::from_args();
+    let results: Vec<(&Challenge, Outcome)> = selected(&options)?
+        .into_iter()
+        .map(|challenge| (challenge, challenge.run()))
+        .collect();
+    if options.json {
+        print_json(&results);
+    } else {
+        print_text(&results);
+    }
+    if results.iter().any(|(c, o)| !o.passed(c)) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
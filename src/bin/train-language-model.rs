@@ -0,0 +1,18 @@
+use anyhow::Result;
+use cryptopals::language_model::LanguageModel;
+use structopt::StructOpt;
+
+/// Trains a language model for scoring plaintexts from a text corpus
+#[derive(StructOpt)]
+struct Options {
+    corpus_file: std::path::PathBuf,
+    model_file: std::path::PathBuf,
+}
+
+fn main() -> Result<()> {
+    let options = Options::from_args();
+    let corpus = std::fs::read(options.corpus_file)?;
+    let model = LanguageModel::train(&corpus)?;
+    model.save(options.model_file)?;
+    Ok(())
+}
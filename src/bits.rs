@@ -0,0 +1,164 @@
+const WORD: usize = std::mem::size_of::<u64>();
+
+fn word(bytes: &[u8]) -> u64 {
+    u64::from_ne_bytes(bytes.try_into().unwrap())
+}
+
+/// The number of differing bits in the common prefix of `l` and `r`.
+pub fn hamming_distance(l: &[u8], r: &[u8]) -> usize {
+    let len = l.len().min(r.len());
+    let (l, r) = (&l[..len], &r[..len]);
+    let l_words = l.chunks_exact(WORD);
+    let r_words = r.chunks_exact(WORD);
+    let tail: usize = l_words
+        .remainder()
+        .iter()
+        .zip(r_words.remainder())
+        .map(|(l, r)| (l ^ r).count_ones() as usize)
+        .sum();
+    l_words
+        .zip(r_words)
+        .map(|(l, r)| (word(l) ^ word(r)).count_ones() as usize)
+        .sum::<usize>()
+        + tail
+}
+
+/// XORs `other` into the common prefix of `data`.
+pub fn xor_into(data: &mut [u8], other: &[u8]) {
+    let len = data.len().min(other.len());
+    let (data, other) = (&mut data[..len], &other[..len]);
+    let mut data_words = data.chunks_exact_mut(WORD);
+    let mut other_words = other.chunks_exact(WORD);
+    for (d, o) in (&mut data_words).zip(&mut other_words) {
+        d.copy_from_slice(&(word(d) ^ word(o)).to_ne_bytes());
+    }
+    for (d, o) in data_words
+        .into_remainder()
+        .iter_mut()
+        .zip(other_words.remainder())
+    {
+        *d ^= o;
+    }
+}
+
+/// The XOR of the common prefix of `l` and `r`.
+pub fn xor(l: &[u8], r: &[u8]) -> Vec<u8> {
+    let mut result = l[..l.len().min(r.len())].to_vec();
+    xor_into(&mut result, r);
+    result
+}
+
+/// Bits are numbered from the least significant bit of the first byte, so bit `index` is bit
+/// `index % 8` of byte `index / 8`.
+pub fn get_bit(data: &[u8], index: usize) -> bool {
+    data[index / 8] & (1 << (index % 8)) != 0
+}
+
+pub fn set_bit(data: &mut [u8], index: usize, value: bool) {
+    if value {
+        data[index / 8] |= 1 << (index % 8);
+    } else {
+        data[index / 8] &= !(1 << (index % 8));
+    }
+}
+
+pub fn flip_bit(data: &mut [u8], index: usize) {
+    data[index / 8] ^= 1 << (index % 8);
+}
+
+/// Transposes the 8x8 bit matrix with one row per byte, so bit `c` of byte `r` becomes bit `r`
+/// of byte `c`.
+pub fn transpose_bits(block: [u8; 8]) -> [u8; 8] {
+    let mut x = u64::from_le_bytes(block);
+    // Swap the off-diagonal 1x1, 2x2 and 4x4 sub-blocks in turn.
+    let t = (x ^ (x >> 7)) & 0x00aa_00aa_00aa_00aa;
+    x ^= t ^ (t << 7);
+    let t = (x ^ (x >> 14)) & 0x0000_cccc_0000_cccc;
+    x ^= t ^ (t << 14);
+    let t = (x ^ (x >> 28)) & 0x0000_0000_f0f0_f0f0;
+    x ^= t ^ (t << 28);
+    x.to_le_bytes()
+}
+
+/// Bit `bit` of every byte of `data`, packed eight to a byte. Panics if `bit` is 8 or more.
+pub fn bit_plane(data: &[u8], bit: u8) -> Vec<u8> {
+    assert!(bit < 8, "bit {} is out of range for a byte", bit);
+    let mut plane = vec![0; data.len().div_ceil(8)];
+    for (index, byte) in data.iter().enumerate() {
+        set_bit(&mut plane, index, byte & (1 << bit) != 0);
+    }
+    plane
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hamming_distance() {
+        assert_eq!(
+            37,
+            hamming_distance("this is a test".as_bytes(), "wokka wokka!!!".as_bytes())
+        );
+        assert_eq!(8, hamming_distance(&[0xff], &[0x00]));
+        assert_eq!(1, hamming_distance(&[0x80], &[0x00, 0xff]));
+        let l: Vec<u8> = (0..100).collect();
+        let r: Vec<u8> = (0..100).map(|b| b ^ 0x81).collect();
+        assert_eq!(200, hamming_distance(&l, &r));
+    }
+
+    #[test]
+    fn test_xor() {
+        let l: Vec<u8> = (0..21).collect();
+        let r: Vec<u8> = (0..20).map(|b| b * 3).collect();
+        let expected: Vec<u8> = l.iter().zip(&r).map(|(l, r)| l ^ r).collect();
+        assert_eq!(expected, xor(&l, &r));
+        let mut data = l.clone();
+        xor_into(&mut data, &r);
+        assert_eq!(expected[..], data[..20]);
+        assert_eq!(20, data[20]);
+    }
+
+    #[test]
+    fn test_get_set_flip_bit() {
+        let mut data = [0u8; 2];
+        set_bit(&mut data, 9, true);
+        assert_eq!([0, 2], data);
+        assert!(get_bit(&data, 9));
+        flip_bit(&mut data, 9);
+        flip_bit(&mut data, 7);
+        assert_eq!([0x80, 0], data);
+        set_bit(&mut data, 7, false);
+        assert_eq!([0, 0], data);
+    }
+
+    #[test]
+    fn test_transpose_bits() {
+        let block = [0x01, 0x03, 0x07, 0x0f, 0x1f, 0x3f, 0x7f, 0xff];
+        let transposed = transpose_bits(block);
+        assert_eq!([0xff, 0xfe, 0xfc, 0xf8, 0xf0, 0xe0, 0xc0, 0x80], transposed);
+        for row in 0..8 {
+            for column in 0..8 {
+                assert_eq!(
+                    get_bit(&block, row * 8 + column),
+                    get_bit(&transposed, column * 8 + row)
+                );
+            }
+        }
+        assert_eq!(block, transpose_bits(transposed));
+    }
+
+    #[test]
+    fn test_bit_plane() {
+        assert_eq!(
+            vec![0b101, 0b1],
+            bit_plane(&[0x80, 0, 0x80, 0, 0, 0, 0, 0, 0x80], 7)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_bit_plane_out_of_range() {
+        bit_plane(b"abc", 8);
+    }
+}
@@ -0,0 +1,232 @@
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::data::{AesKey, Ciphertext, Iv, Key, Plaintext};
+use crate::error::CryptoError;
+use crate::set1::challenge1::{base64_decode_homebrew, hex_to_base64, Base64Config};
+use crate::set1::challenge2::fixed_xor_hex;
+use crate::set1::challenge3::break_single_byte_xor;
+use crate::set1::challenge4::detect_single_byte_xor;
+use crate::set1::challenge5::repeating_key_xor_encrypt;
+use crate::set1::challenge6::break_repeating_key_xor;
+use crate::set1::challenge7::aes128_ecb_decrypt;
+use crate::set1::challenge8::find_ciphertext_with_most_repetitions;
+use crate::set2::challenge10::aes128_cbc_decrypt;
+use crate::set2::challenge11::{encryption_oracle, guess_encryption_mode, EncryptionMode};
+use crate::set2::challenge9::pkcs7_pad;
+
+/// A challenge from cryptopals.com, with its input and the answer it should give.
+pub struct Challenge {
+    pub set: u32,
+    pub number: u32,
+    pub title: &'static str,
+    /// The input given with the challenge, e.g. the contents of its data file.
+    pub input: fn() -> &'static str,
+    /// Solves the challenge for `input`. Long answers such as plaintexts are cut down to their
+    /// first line.
+    pub solve: fn(&str) -> Result<String, CryptoError>,
+    pub expected: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub answer: Result<String, CryptoError>,
+    pub duration: Duration,
+}
+
+impl Outcome {
+    pub fn passed(&self, challenge: &Challenge) -> bool {
+        self.answer.as_deref() == Ok(challenge.expected)
+    }
+}
+
+impl Challenge {
+    /// Solves the challenge, timing only the solver.
+    pub fn run(&self) -> Outcome {
+        let input = (self.input)();
+        let start = Instant::now();
+        let answer = (self.solve)(input);
+        Outcome {
+            answer,
+            duration: start.elapsed(),
+        }
+    }
+}
+
+pub static CHALLENGES: [Challenge; 11] = [
+    Challenge {
+        set: 1,
+        number: 1,
+        title: "Convert hex to base64",
+        input: || "49276d206b696c6c696e6720796f757220627261696e206c696b65206120706f69736f6e6f7573206d757368726f6f6d",
+        solve: hex_to_base64,
+        expected: "SSdtIGtpbGxpbmcgeW91ciBicmFpbiBsaWtlIGEgcG9pc29ub3VzIG11c2hyb29t",
+    },
+    Challenge {
+        set: 1,
+        number: 2,
+        title: "Fixed XOR",
+        input: || "1c0111001f010100061a024b53535009181c\n686974207468652062756c6c277320657965",
+        solve: |input| match input.split_once('\n') {
+            Some((l, r)) => fixed_xor_hex(l, r),
+            None => Err(CryptoError::EmptyInput),
+        },
+        expected: "746865206b696420646f6e277420706c6179",
+    },
+    Challenge {
+        set: 1,
+        number: 3,
+        title: "Single-byte XOR cipher",
+        input: || "1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736",
+        solve: |input| Ok(break_single_byte_xor(&hex::decode(input)?)?.1),
+        expected: "Cooking MC's like a pound of bacon",
+    },
+    Challenge {
+        set: 1,
+        number: 4,
+        title: "Detect single-character XOR",
+        input: || include_str!("set1/4.txt"),
+        solve: |input| {
+            let ciphertexts = input
+                .lines()
+                .map(hex::decode)
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(first_line(detect_single_byte_xor(&ciphertexts)?.as_bytes()))
+        },
+        expected: "Now that the party is jumping",
+    },
+    Challenge {
+        set: 1,
+        number: 5,
+        title: "Implement repeating-key XOR",
+        input: || "Burning 'em, if you ain't quick and nimble\nI go crazy when I hear a cymbal",
+        solve: |input| {
+            let plaintext = Plaintext::from_str(input).unwrap();
+            Ok(repeating_key_xor_encrypt(&plaintext, &Key::from_str("ICE").unwrap())?.to_hex())
+        },
+        expected: "0b3637272a2b2e63622c2e69692a23693a2a3c6324202d623d63343c2a26226324272765272a282b2f20430a652e2c652a3124333a653e2b2027630c692b20283165286326302e27282f",
+    },
+    Challenge {
+        set: 1,
+        number: 6,
+        title: "Break repeating-key XOR",
+        input: || include_str!("set1/6.txt"),
+        solve: |input| {
+            let ciphertext = Ciphertext(base64_decode_homebrew(input, Base64Config::MIME)?);
+            let (key, _plaintext) = break_repeating_key_xor(&ciphertext)?;
+            Ok(String::from_utf8_lossy(&key.0).to_string())
+        },
+        expected: "Terminator X: Bring the noise",
+    },
+    Challenge {
+        set: 1,
+        number: 7,
+        title: "AES in ECB mode",
+        input: || include_str!("set1/7.txt"),
+        solve: |input| {
+            let ciphertext = Ciphertext(base64_decode_homebrew(input, Base64Config::MIME)?);
+            let key = AesKey::aes128(b"YELLOW SUBMARINE")?;
+            Ok(first_line(&aes128_ecb_decrypt(&ciphertext, &key)?.0))
+        },
+        expected: "I'm back and I'm ringin' the bell",
+    },
+    Challenge {
+        set: 1,
+        number: 8,
+        title: "Detect AES in ECB mode",
+        input: || include_str!("set1/8.txt"),
+        solve: |input| {
+            let ciphertexts = input
+                .lines()
+                .map(|line| hex::decode(line).map(Ciphertext))
+                .collect::<Result<Vec<_>, _>>()?;
+            let ecb = find_ciphertext_with_most_repetitions(16, &ciphertexts)?;
+            let line = ciphertexts.iter().position(|c| c == ecb).unwrap() + 1;
+            Ok(format!("line {}", line))
+        },
+        expected: "line 133",
+    },
+    Challenge {
+        set: 2,
+        number: 9,
+        title: "Implement PKCS#7 padding",
+        input: || "YELLOW SUBMARINE",
+        solve: |input| {
+            let padded = pkcs7_pad(&Plaintext::from_str(input).unwrap(), 20)?;
+            Ok(padded.0.escape_ascii().to_string())
+        },
+        expected: "YELLOW SUBMARINE\\x04\\x04\\x04\\x04",
+    },
+    Challenge {
+        set: 2,
+        number: 10,
+        title: "Implement CBC mode",
+        input: || include_str!("set2/10.txt"),
+        solve: |input| {
+            let ciphertext = Ciphertext(base64_decode_homebrew(input, Base64Config::MIME)?);
+            let key = AesKey::aes128(b"YELLOW SUBMARINE")?;
+            let iv = Iv::block(&[0; 16])?;
+            Ok(first_line(&aes128_cbc_decrypt(&ciphertext, &key, iv)?.0))
+        },
+        expected: "I'm back and I'm ringin' the bell",
+    },
+    Challenge {
+        set: 2,
+        number: 11,
+        title: "An ECB/CBC detection oracle",
+        input: || "YELLOW SUBMARINEYELLOW SUBMARINEYELLOW SUBMARINE",
+        solve: |input| {
+            let plaintext = Plaintext::from_str(input).unwrap();
+            let mut correct = 0;
+            for mode in [EncryptionMode::ECB, EncryptionMode::CBC] {
+                for _ in 0..50 {
+                    let ciphertext = encryption_oracle(&plaintext, Some(mode))?;
+                    if guess_encryption_mode(&ciphertext)? == mode {
+                        correct += 1;
+                    }
+                }
+            }
+            Ok(format!("{}/100 modes detected", correct))
+        },
+        expected: "100/100 modes detected",
+    },
+];
+
+pub fn challenge(number: u32) -> Option<&'static Challenge> {
+    CHALLENGES.iter().find(|c| c.number == number)
+}
+
+pub fn challenges_in_set(set: u32) -> impl Iterator<Item = &'static Challenge> {
+    CHALLENGES.iter().filter(move |c| c.set == set)
+}
+
+fn first_line(text: &[u8]) -> String {
+    let text = String::from_utf8_lossy(text);
+    text.lines()
+        .next()
+        .unwrap_or_default()
+        .trim_end()
+        .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_all_challenges_pass() {
+        for challenge in &CHALLENGES {
+            let outcome = challenge.run();
+            assert_eq!(Ok(challenge.expected), outcome.answer.as_deref());
+            assert!(outcome.passed(challenge));
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!("Implement CBC mode", challenge(10).unwrap().title);
+        assert!(challenge(12).is_none());
+        assert_eq!(8, challenges_in_set(1).count());
+        assert_eq!(3, challenges_in_set(2).count());
+    }
+}
@@ -0,0 +1,78 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::data::Encoding;
+use crate::error::CryptoError;
+
+/// Reads a whole file, or stdin if `path` is `-`.
+pub fn read_input(path: &Path) -> Result<Vec<u8>, CryptoError> {
+    if is_std_stream(path) {
+        let mut data = vec![];
+        std::io::stdin().read_to_end(&mut data)?;
+        Ok(data)
+    } else {
+        Ok(std::fs::read(path)?)
+    }
+}
+
+/// Reads and decodes a whole file, or stdin if `path` is `-`.
+pub fn read_encoded(path: &Path, encoding: Encoding) -> Result<Vec<u8>, CryptoError> {
+    encoding.decode(&read_input(path)?)
+}
+
+/// Encodes and writes `data` to a file, or stdout if `path` is `-`. Hex and base64 get a
+/// trailing newline, so they read well in a terminal.
+pub fn write_output(path: &Path, data: &[u8], encoding: Encoding) -> Result<(), CryptoError> {
+    let mut data = encoding.encode(data);
+    if encoding != Encoding::Raw {
+        data.push(b'\n');
+    }
+    if is_std_stream(path) {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(&data)?;
+        stdout.flush()?;
+    } else {
+        std::fs::write(path, data)?;
+    }
+    Ok(())
+}
+
+/// Opens a file, or stdin if `path` is `-`, for reading a bit at a time.
+pub fn open_input(path: &Path) -> Result<Box<dyn Read>, CryptoError> {
+    if is_std_stream(path) {
+        Ok(Box::new(std::io::stdin().lock()))
+    } else {
+        Ok(Box::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+/// Creates a file, or uses stdout if `path` is `-`, for writing a bit at a time.
+pub fn create_output(path: &Path) -> Result<Box<dyn Write>, CryptoError> {
+    if is_std_stream(path) {
+        Ok(Box::new(std::io::stdout().lock()))
+    } else {
+        Ok(Box::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+pub fn is_std_stream(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write_and_read() {
+        let path = std::env::temp_dir().join(format!("cryptopals-{}.cli", std::process::id()));
+        write_output(&path, b"\x00\xff", Encoding::Hex).unwrap();
+        let raw = read_input(&path).unwrap();
+        let decoded = read_encoded(&path, Encoding::Hex);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(b"00ff\n".to_vec(), raw);
+        assert_eq!(Ok(vec![0x00, 0xff]), decoded);
+        assert!(is_std_stream(Path::new("-")));
+    }
+}
@@ -0,0 +1,192 @@
+use crate::data::Ciphertext;
+use crate::error::CryptoError;
+use crate::scoring::Scorer;
+
+/// Breaks several ciphertexts that were XOR'ed with the same keystream, by guessing parts of
+/// the plaintexts and building up the keystream one guess at a time.
+#[derive(Debug, Clone)]
+pub struct CribDrag {
+    ciphertexts: Vec<Ciphertext>,
+    keystream: Vec<Option<u8>>,
+}
+
+/// Where a crib gives a plausible fragment: if one message of the pair has the crib at
+/// `offset`, the other has `fragment` there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CribMatch {
+    pub pair: (usize, usize),
+    pub offset: usize,
+    pub fragment: Vec<u8>,
+    pub score: f64,
+}
+
+impl CribDrag {
+    pub fn new(ciphertexts: Vec<Ciphertext>) -> Result<CribDrag, CryptoError> {
+        let longest = ciphertexts.iter().map(|c| c.len()).max().unwrap_or(0);
+        if longest == 0 {
+            return Err(CryptoError::EmptyInput);
+        }
+        Ok(CribDrag {
+            ciphertexts,
+            keystream: vec![None; longest],
+        })
+    }
+
+    pub fn ciphertexts(&self) -> &[Ciphertext] {
+        &self.ciphertexts
+    }
+
+    /// The keystream bytes found so far.
+    pub fn keystream(&self) -> &[Option<u8>] {
+        &self.keystream
+    }
+
+    /// Slides `crib` across every offset of every pair of ciphertexts XOR'ed together and
+    /// returns the fragments it gives, most plausible first.
+    pub fn drag(&self, crib: &[u8], scorer: &dyn Scorer) -> Vec<CribMatch> {
+        let mut matches = vec![];
+        if crib.is_empty() {
+            return matches;
+        }
+        for first in 0..self.ciphertexts.len() {
+            for second in first + 1..self.ciphertexts.len() {
+                let xored = &self.ciphertexts[first] ^ &self.ciphertexts[second];
+                for (offset, window) in xored.windows(crib.len()).enumerate() {
+                    let fragment: Vec<u8> = window.iter().zip(crib).map(|(x, c)| x ^ c).collect();
+                    matches.push(CribMatch {
+                        pair: (first, second),
+                        offset,
+                        score: scorer.score(&fragment),
+                        fragment,
+                    });
+                }
+            }
+        }
+        matches.sort_by(|l, r| r.score.total_cmp(&l.score));
+        matches
+    }
+
+    /// Records that `message` has `plaintext` at `offset`, which gives the keystream there.
+    /// Later guesses overwrite earlier ones.
+    pub fn guess(
+        &mut self,
+        message: usize,
+        offset: usize,
+        plaintext: &[u8],
+    ) -> Result<(), CryptoError> {
+        let ciphertext = self
+            .ciphertexts
+            .get(message)
+            .ok_or(CryptoError::IndexOutOfRange {
+                index: message,
+                len: self.ciphertexts.len(),
+            })?;
+        let end = offset + plaintext.len();
+        if end > ciphertext.len() {
+            return Err(CryptoError::InputTooShort {
+                min_len: end,
+                len: ciphertext.len(),
+            });
+        }
+        for (index, byte) in plaintext.iter().enumerate() {
+            self.keystream[offset + index] = Some(ciphertext[offset + index] ^ byte);
+        }
+        Ok(())
+    }
+
+    /// The plaintext of every message as far as the keystream is known.
+    pub fn decryptions(&self) -> Vec<Vec<Option<u8>>> {
+        self.ciphertexts
+            .iter()
+            .map(|ciphertext| {
+                ciphertext
+                    .as_bytes()
+                    .iter()
+                    .zip(&self.keystream)
+                    .map(|(c, k)| k.map(|k| c ^ k))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The partial decryptions as text, with `_` for unknown bytes and `.` for unprintable ones.
+    pub fn render(&self) -> Vec<String> {
+        self.decryptions()
+            .iter()
+            .map(|plaintext| {
+                plaintext
+                    .iter()
+                    .map(|byte| match byte {
+                        Some(b) if b.is_ascii_graphic() || *b == b' ' => *b as char,
+                        Some(_) => '.',
+                        None => '_',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::language_model::LanguageModel;
+
+    fn encrypt_all(plaintexts: &[&[u8]]) -> Vec<Ciphertext> {
+        let keystream: Vec<u8> = (0..64u8).map(|i| i.wrapping_mul(97) ^ 0x5a).collect();
+        plaintexts
+            .iter()
+            .map(|p| p.iter().zip(&keystream).map(|(p, k)| p ^ k).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_drag_finds_crib() {
+        let ciphertexts = encrypt_all(&[
+            b"Meet me at the old bridge",
+            b"Bring the money tomorrow",
+            b"Send more soldiers",
+        ]);
+        let crib_drag = CribDrag::new(ciphertexts).unwrap();
+        let matches = crib_drag.drag(b"the ", LanguageModel::english());
+        assert!(matches.windows(2).all(|w| w[0].score >= w[1].score));
+        let position = |pair, offset| {
+            matches
+                .iter()
+                .position(|m| m.pair == pair && m.offset == offset)
+                .unwrap()
+        };
+        // "the " is at offset 11 in the first message, where the second has "oney".
+        assert_eq!(b"oney".to_vec(), matches[position((0, 1), 11)].fragment);
+        assert!(position((0, 1), 11) < matches.len() / 10);
+    }
+
+    #[test]
+    fn test_guess() {
+        let ciphertexts = encrypt_all(&[b"Meet me at the old bridge", b"Bring the money"]);
+        let mut crib_drag = CribDrag::new(ciphertexts).unwrap();
+        crib_drag.guess(0, 0, b"Meet").unwrap();
+        crib_drag.guess(1, 6, b"the").unwrap();
+        assert_eq!(
+            vec!["Meet__e a________________", "Brin__the______"],
+            crib_drag.render()
+        );
+        assert_eq!(
+            Err(CryptoError::IndexOutOfRange { index: 2, len: 2 }),
+            crib_drag.guess(2, 0, b"x")
+        );
+        assert_eq!(
+            Err(CryptoError::InputTooShort {
+                min_len: 16,
+                len: 15
+            }),
+            crib_drag.guess(1, 12, b"ney!")
+        );
+    }
+
+    #[test]
+    fn test_new_without_ciphertexts() {
+        assert!(CribDrag::new(vec![]).is_err());
+        assert!(CribDrag::new(vec![Ciphertext(vec![])]).is_err());
+    }
+}
@@ -1,30 +1,183 @@
 use std::convert::Infallible;
+use std::ops::{BitXor, Index};
+use std::slice::{Chunks, ChunksExact, SliceIndex};
 
+use crate::bits;
+use crate::error::CryptoError;
+use crate::set1::challenge1::{base64_decode_homebrew, Base64Config};
+
+/// A key of any length. The block cipher modes check it against the cipher's key size.
+#[derive(Clone, Eq, PartialEq)]
 pub struct Key(pub Vec<u8>);
 
-pub struct Iv(pub Vec<u8>);
+/// A key of one of the AES key lengths. All the ways of making one check the length. It derefs
+/// to `Key`, so it can be passed to the generic block cipher modes as is.
+#[derive(Clone, Eq, PartialEq)]
+pub struct AesKey(Key);
+
+/// An IV of at least one byte. Its length is checked against the cipher's block size when it is
+/// used, or up front with `Iv::new`. Unlike `Key` the bytes can't be set directly, use
+/// `as_bytes` to read them.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Iv(Vec<u8>);
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Plaintext(pub Vec<u8>);
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Ciphertext(pub Vec<u8>);
 
-impl std::string::ToString for Key {
-    fn to_string(&self) -> String {
-        String::from_utf8_lossy(&self.0).to_string()
+impl Key {
+    /// Opts in to showing the key material, which `Debug` and `Display` otherwise redact.
+    pub fn reveal(&self) -> Revealed<'_> {
+        Revealed(&self.0)
+    }
+}
+
+impl AesKey {
+    pub fn aes128(bytes: &[u8]) -> Result<AesKey, CryptoError> {
+        AesKey::with_length(bytes, &[16])
+    }
+
+    pub fn aes192(bytes: &[u8]) -> Result<AesKey, CryptoError> {
+        AesKey::with_length(bytes, &[24])
+    }
+
+    pub fn aes256(bytes: &[u8]) -> Result<AesKey, CryptoError> {
+        AesKey::with_length(bytes, &[32])
+    }
+
+    /// Any valid AES key length.
+    pub fn new(bytes: &[u8]) -> Result<AesKey, CryptoError> {
+        AesKey::with_length(bytes, &[16, 24, 32])
+    }
+
+    fn with_length(bytes: &[u8], expected: &'static [usize]) -> Result<AesKey, CryptoError> {
+        if !expected.contains(&bytes.len()) {
+            return Err(CryptoError::InvalidKeyLength {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+        Ok(AesKey(Key(bytes.to_vec())))
+    }
+}
+
+impl std::ops::Deref for AesKey {
+    type Target = Key;
+
+    fn deref(&self) -> &Key {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for AesKey {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+}
+
+impl Iv {
+    /// An IV for a cipher with `block_size` byte blocks, e.g. `C::BLOCK_SIZE`.
+    pub fn new(bytes: &[u8], block_size: usize) -> Result<Iv, CryptoError> {
+        if bytes.len() != block_size {
+            return Err(CryptoError::InvalidIvLength {
+                expected: block_size,
+                actual: bytes.len(),
+            });
+        }
+        Iv::try_from(bytes.to_vec())
+    }
+
+    /// An IV for a cipher with 16 byte blocks, such as AES.
+    pub fn block(bytes: &[u8]) -> Result<Iv, CryptoError> {
+        Iv::new(bytes, 16)
+    }
+
+    pub fn reveal(&self) -> Revealed<'_> {
+        Revealed(&self.0)
+    }
+}
+
+/// Secret bytes that were explicitly asked for. `Display` shows them as (lossy) text and
+/// `Debug` as hex.
+pub struct Revealed<'a>(&'a [u8]);
+
+impl std::fmt::Display for Revealed<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(self.0))
+    }
+}
+
+impl std::fmt::Debug for Revealed<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
+/// Redacts `Debug` and `Display` and zeroizes the bytes on drop, spare capacity included. Wiping
+/// is best effort: clones are wiped when they are dropped, but the old buffer left behind when a
+/// `Vec` grew, and copies taken of `as_bytes` or `reveal`, are not.
+macro_rules! secret {
+    ($name:ident) => {
+        impl std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(
+                    f,
+                    "{}(<redacted {} bytes>)",
+                    stringify!($name),
+                    self.0.len()
+                )
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "<redacted {} byte {}>", self.0.len(), stringify!($name))
+            }
+        }
+    };
+}
+
+secret!(Key);
+secret!(AesKey);
+secret!(Iv);
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        zeroize(&mut self.0);
+    }
+}
+
+impl Drop for Iv {
+    fn drop(&mut self) {
+        zeroize(&mut self.0);
     }
 }
 
-impl std::string::ToString for Plaintext {
-    fn to_string(&self) -> String {
-        String::from_utf8_lossy(&self.0).to_string()
+/// Overwrites `bytes`, and the capacity past its length, with zeros in a way the optimizer
+/// won't remove as a dead store.
+fn zeroize(bytes: &mut Vec<u8>) {
+    for byte in bytes.iter_mut() {
+        // SAFETY: `byte` is a valid, aligned and exclusive reference.
+        unsafe { std::ptr::write_volatile(byte, 0) };
     }
+    for byte in bytes.spare_capacity_mut() {
+        // SAFETY: `byte` is in the allocation and valid for writes, though not initialized.
+        unsafe { std::ptr::write_volatile(byte.as_mut_ptr(), 0) };
+    }
+    std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
 }
 
-impl std::string::ToString for Ciphertext {
-    fn to_string(&self) -> String {
-        String::from_utf8_lossy(&self.0).to_string()
+impl std::fmt::Display for Plaintext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
+    }
+}
+
+impl std::fmt::Display for Ciphertext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
     }
 }
 
@@ -36,6 +189,37 @@ impl std::str::FromStr for Key {
     }
 }
 
+/// The text as the key, e.g. `"YELLOW SUBMARINE"`.
+impl std::str::FromStr for AesKey {
+    type Err = CryptoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AesKey::new(s.as_bytes())
+    }
+}
+
+impl TryFrom<Vec<u8>> for AesKey {
+    type Error = CryptoError;
+
+    fn try_from(mut bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        let key = AesKey::new(&bytes);
+        zeroize(&mut bytes);
+        key
+    }
+}
+
+/// Any length but zero, see `Iv::new` to check the length for a cipher.
+impl TryFrom<Vec<u8>> for Iv {
+    type Error = CryptoError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        if bytes.is_empty() {
+            return Err(CryptoError::EmptyInput);
+        }
+        Ok(Iv(bytes))
+    }
+}
+
 impl std::str::FromStr for Plaintext {
     type Err = Infallible;
 
@@ -55,3 +239,407 @@ impl std::iter::FromIterator<u8> for Key {
         Key(i.into_iter().collect())
     }
 }
+
+impl std::iter::FromIterator<u8> for Plaintext {
+    fn from_iter<I: IntoIterator<Item = u8>>(i: I) -> Self {
+        Plaintext(i.into_iter().collect())
+    }
+}
+
+/// Goes through `TryFrom`, so the buffers that check their length do so.
+fn from_vec<T>(bytes: Vec<u8>) -> Result<T, CryptoError>
+where
+    T: TryFrom<Vec<u8>>,
+    CryptoError: From<T::Error>,
+{
+    Ok(T::try_from(bytes)?)
+}
+
+macro_rules! byte_buffer {
+    ($name:ident) => {
+        impl $name {
+            pub fn as_bytes(&self) -> &[u8] {
+                &self.0
+            }
+
+            pub fn len(&self) -> usize {
+                self.0.len()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.0.is_empty()
+            }
+
+            /// The last block may be shorter than `block_size`.
+            pub fn blocks(&self, block_size: usize) -> Result<Chunks<'_, u8>, CryptoError> {
+                if block_size == 0 {
+                    return Err(CryptoError::InvalidBlockSize(block_size));
+                }
+                Ok(self.0.chunks(block_size))
+            }
+
+            /// Like `blocks`, but fails unless the length is a multiple of `block_size`.
+            pub fn exact_blocks(
+                &self,
+                block_size: usize,
+            ) -> Result<ChunksExact<'_, u8>, CryptoError> {
+                if block_size == 0 {
+                    return Err(CryptoError::InvalidBlockSize(block_size));
+                }
+                if !self.0.len().is_multiple_of(block_size) {
+                    return Err(CryptoError::NotBlockAligned {
+                        len: self.0.len(),
+                        block_size,
+                    });
+                }
+                Ok(self.0.chunks_exact(block_size))
+            }
+
+            pub fn to_hex(&self) -> String {
+                hex::encode(&self.0)
+            }
+
+            pub fn to_base64(&self) -> String {
+                base64::encode(&self.0)
+            }
+
+            pub fn from_hex(s: &str) -> Result<Self, CryptoError> {
+                from_vec(hex::decode(s.trim())?)
+            }
+
+            /// Accepts line wrapped input.
+            pub fn from_base64(s: &str) -> Result<Self, CryptoError> {
+                from_vec(base64_decode_homebrew(s, Base64Config::MIME)?)
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl<I: SliceIndex<[u8]>> Index<I> for $name {
+            type Output = I::Output;
+
+            fn index(&self, index: I) -> &Self::Output {
+                &self.0[index]
+            }
+        }
+    };
+}
+
+byte_buffer!(Key);
+byte_buffer!(Iv);
+byte_buffer!(Plaintext);
+byte_buffer!(Ciphertext);
+
+/// Any bytes make a valid key, only `AesKey` and `Iv` have length requirements.
+impl From<Vec<u8>> for Key {
+    fn from(bytes: Vec<u8>) -> Self {
+        Key(bytes)
+    }
+}
+
+/// The buffers that aren't secret can be taken apart and put together without checks.
+macro_rules! public_buffer {
+    ($name:ident) => {
+        impl $name {
+            pub fn into_bytes(self) -> Vec<u8> {
+                self.0
+            }
+        }
+
+        impl From<Vec<u8>> for $name {
+            fn from(bytes: Vec<u8>) -> Self {
+                $name(bytes)
+            }
+        }
+    };
+}
+
+public_buffer!(Plaintext);
+public_buffer!(Ciphertext);
+
+/// Displays and parses the wrapped bytes as hex, e.g. `Hex(&ciphertext).to_string()` or
+/// `"0b3637".parse::<Hex<Ciphertext>>()`.
+pub struct Hex<T>(pub T);
+
+/// Displays and parses the wrapped bytes as standard base64.
+pub struct Base64<T>(pub T);
+
+impl<T: AsRef<[u8]>> std::fmt::Display for Hex<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", hex::encode(self.0.as_ref()))
+    }
+}
+
+impl<T: AsRef<[u8]>> std::fmt::Display for Base64<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", base64::encode(self.0.as_ref()))
+    }
+}
+
+impl<T> std::str::FromStr for Hex<T>
+where
+    T: TryFrom<Vec<u8>>,
+    CryptoError: From<T::Error>,
+{
+    type Err = CryptoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Hex(from_vec(hex::decode(s.trim())?)?))
+    }
+}
+
+impl<T> std::str::FromStr for Base64<T>
+where
+    T: TryFrom<Vec<u8>>,
+    CryptoError: From<T::Error>,
+{
+    type Err = CryptoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Base64(from_vec(base64_decode_homebrew(
+            s,
+            Base64Config::MIME,
+        )?)?))
+    }
+}
+
+/// How bytes are stored in a file or passed on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Raw,
+    Hex,
+    Base64,
+}
+
+impl Encoding {
+    /// Hex and base64 may be spread over several lines.
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        match self {
+            Encoding::Raw => Ok(data.to_vec()),
+            Encoding::Hex => {
+                let digits: Vec<u8> = data
+                    .iter()
+                    .copied()
+                    .filter(|b| !b.is_ascii_whitespace())
+                    .collect();
+                Ok(hex::decode(digits)?)
+            }
+            Encoding::Base64 => Ok(base64_decode_homebrew(
+                &String::from_utf8_lossy(data),
+                Base64Config::MIME,
+            )?),
+        }
+    }
+
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Encoding::Raw => data.to_vec(),
+            Encoding::Hex => hex::encode(data).into_bytes(),
+            Encoding::Base64 => base64::encode(data).into_bytes(),
+        }
+    }
+}
+
+impl std::str::FromStr for Encoding {
+    type Err = CryptoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(Encoding::Raw),
+            "hex" => Ok(Encoding::Hex),
+            "base64" => Ok(Encoding::Base64),
+            _ => Err(CryptoError::InvalidParameter(
+                "Unknown encoding, expected raw, hex or base64",
+            )),
+        }
+    }
+}
+
+fn xor_with_cycled_key(data: &[u8], key: &[u8]) -> Vec<u8> {
+    data.iter()
+        .zip(key.iter().cycle())
+        .map(|(d, k)| d ^ k)
+        .collect()
+}
+
+/// Encrypts with the key repeated over the whole plaintext. An empty key gives an empty
+/// result, use `repeating_key_xor_encrypt` to get an error instead.
+impl BitXor<&Key> for &Plaintext {
+    type Output = Ciphertext;
+
+    fn bitxor(self, key: &Key) -> Ciphertext {
+        Ciphertext(xor_with_cycled_key(&self.0, &key.0))
+    }
+}
+
+/// Decrypts with the key repeated over the whole ciphertext.
+impl BitXor<&Key> for &Ciphertext {
+    type Output = Plaintext;
+
+    fn bitxor(self, key: &Key) -> Plaintext {
+        Plaintext(xor_with_cycled_key(&self.0, &key.0))
+    }
+}
+
+/// Recovers the keystream from a known plaintext. The result is as long as the shorter input.
+impl BitXor<&Plaintext> for &Ciphertext {
+    type Output = Key;
+
+    fn bitxor(self, plaintext: &Plaintext) -> Key {
+        Key(bits::xor(&self.0, &plaintext.0))
+    }
+}
+
+/// Two ciphertexts under the same keystream XOR to the XOR of their plaintexts. The result is
+/// as long as the shorter input.
+impl BitXor<&Ciphertext> for &Ciphertext {
+    type Output = Vec<u8>;
+
+    fn bitxor(self, other: &Ciphertext) -> Vec<u8> {
+        bits::xor(&self.0, &other.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_encoding() {
+        let data = b"\x00\xffYELLOW";
+        for encoding in [Encoding::Raw, Encoding::Hex, Encoding::Base64] {
+            let encoded = encoding.encode(data);
+            assert_eq!(data.to_vec(), encoding.decode(&encoded).unwrap());
+        }
+        assert_eq!(vec![0xab, 0xcd], Encoding::Hex.decode(b"ab\ncd\n").unwrap());
+        assert_eq!(Ok(Encoding::Base64), Encoding::from_str("base64"));
+        assert!(Encoding::from_str("rot13").is_err());
+    }
+
+    #[test]
+    fn test_xor_operators() {
+        let plaintext = Plaintext::from_str("Burning 'em, if you ain't quick").unwrap();
+        let key = Key::from_str("ICE").unwrap();
+        let ciphertext = &plaintext ^ &key;
+        assert_eq!(
+            "0b3637272a2b2e63622c2e69692a23693a2a3c",
+            &ciphertext.to_hex()[..38]
+        );
+        assert_eq!(plaintext, &ciphertext ^ &key);
+        assert_eq!(
+            b"ICEICE",
+            (&ciphertext ^ &Plaintext(b"Burnin".to_vec())).as_bytes()
+        );
+        assert_eq!(vec![0; 31], &ciphertext ^ &ciphertext);
+    }
+
+    #[test]
+    fn test_blocks() {
+        let ciphertext = Ciphertext((0..20).collect());
+        assert_eq!(2, ciphertext.blocks(16).unwrap().count());
+        assert_eq!(
+            CryptoError::NotBlockAligned {
+                len: 20,
+                block_size: 16
+            },
+            ciphertext.exact_blocks(16).unwrap_err()
+        );
+        assert_eq!(5, ciphertext.exact_blocks(4).unwrap().count());
+        assert_eq!(
+            CryptoError::InvalidBlockSize(0),
+            ciphertext.blocks(0).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_slicing() {
+        let ciphertext = Ciphertext((0..20).collect());
+        assert_eq!([16, 17, 18, 19], ciphertext[16..]);
+        assert_eq!(3, ciphertext[3]);
+    }
+
+    #[test]
+    fn test_hex_and_base64() {
+        let ciphertext = Ciphertext::from_hex("49276d").unwrap();
+        assert_eq!(b"I'm", ciphertext.as_bytes());
+        assert_eq!("49276d", Hex(&ciphertext).to_string());
+        assert_eq!("SSdt", Base64(&ciphertext).to_string());
+        let parsed: Base64<Ciphertext> = "SS\ndt".parse().unwrap();
+        assert_eq!(ciphertext, parsed.0);
+        let parsed: Hex<Key> = "49276d".parse().unwrap();
+        assert_eq!(b"I'm", parsed.0.as_bytes());
+        assert!("49276".parse::<Hex<Key>>().is_err());
+        assert_eq!(
+            ciphertext,
+            Ciphertext::from_base64(&ciphertext.to_base64()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_key_constructors() {
+        assert!(AesKey::aes128(b"YELLOW SUBMARINE").is_ok());
+        assert_eq!(
+            CryptoError::InvalidKeyLength {
+                expected: &[16],
+                actual: 17
+            },
+            AesKey::aes128(b"YELLOW SUBMARINES").unwrap_err()
+        );
+        assert!(AesKey::aes192(&[0; 24]).is_ok());
+        assert!(AesKey::aes256(&[0; 16]).is_err());
+        assert!(AesKey::new(&[0; 32]).is_ok());
+        assert!(AesKey::new(&[0; 20]).is_err());
+        assert!(Iv::block(&[0; 16]).is_ok());
+        assert_eq!(
+            CryptoError::InvalidIvLength {
+                expected: 16,
+                actual: 8
+            },
+            Iv::block(&[0; 8]).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_conversions_check_lengths() {
+        assert!(AesKey::from_str("YELLOW SUBMARINE").is_ok());
+        assert!(AesKey::from_str("YELLOW").is_err());
+        assert!(AesKey::try_from(vec![0; 24]).is_ok());
+        assert!("00ff".parse::<Hex<AesKey>>().is_err());
+        assert!(Iv::try_from(vec![0; 8]).is_ok());
+        assert!(Iv::try_from(vec![]).is_err());
+        assert!(Iv::new(&[0; 8], 8).is_ok());
+        assert!(Iv::new(&[0; 15], 16).is_err());
+        assert!(Iv::from_base64(&base64::encode([0; 16])).is_ok());
+        assert!("".parse::<Hex<Iv>>().is_err());
+        assert_eq!(b"I'm", Key::from_hex("49276d").unwrap().as_bytes());
+    }
+
+    #[test]
+    fn test_secrets_are_redacted() {
+        let key = AesKey::aes128(b"YELLOW SUBMARINE").unwrap();
+        assert_eq!("AesKey(<redacted 16 bytes>)", format!("{:?}", key));
+        assert_eq!("<redacted 16 byte AesKey>", key.to_string());
+        assert_eq!("YELLOW SUBMARINE", key.reveal().to_string());
+        let key = Key::from_str("ICE").unwrap();
+        assert_eq!("Key(<redacted 3 bytes>)", format!("{:?}", key));
+        let iv = Iv::block(&[0xab; 16]).unwrap();
+        assert_eq!("Iv(<redacted 16 bytes>)", format!("{:?}", iv));
+        assert_eq!("ab".repeat(16), format!("{:?}", iv.reveal()));
+    }
+
+    #[test]
+    fn test_zeroize() {
+        let mut secret = Vec::with_capacity(32);
+        secret.extend_from_slice(b"YELLOW SUBMARINE");
+        zeroize(&mut secret);
+        assert_eq!(vec![0; 16], secret);
+        // SAFETY: zeroize wrote all of the capacity.
+        unsafe { secret.set_len(secret.capacity()) };
+        assert!(secret.iter().all(|b| *b == 0));
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt;
+
+use crate::set1::challenge1::Base64DecodeError;
+use crate::set2::challenge9::PaddingError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CryptoError {
+    InvalidKeyLength {
+        expected: &'static [usize],
+        actual: usize,
+    },
+    InvalidIvLength {
+        expected: usize,
+        actual: usize,
+    },
+    InvalidNonceLength {
+        expected: usize,
+        actual: usize,
+    },
+    InvalidBlockSize(usize),
+    NotBlockAligned {
+        len: usize,
+        block_size: usize,
+    },
+    Padding(PaddingError),
+    Hex(hex::FromHexError),
+    Base64(Base64DecodeError),
+    LengthMismatch {
+        left: usize,
+        right: usize,
+    },
+    EmptyInput,
+    InputTooShort {
+        min_len: usize,
+        len: usize,
+    },
+    CounterOverflow,
+    IndexOutOfRange {
+        index: usize,
+        len: usize,
+    },
+    Io {
+        kind: std::io::ErrorKind,
+        message: String,
+    },
+    InvalidModel(&'static str),
+    InvalidParameter(&'static str),
+    InvalidRecipe {
+        line: usize,
+        message: String,
+    },
+    RecipeStep {
+        step: usize,
+        operation: &'static str,
+        error: Box<CryptoError>,
+    },
+    WorkerFailed,
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoError::InvalidKeyLength { expected, actual } => write!(
+                f,
+                "Invalid key length {}, expected {}",
+                actual,
+                one_of(expected)
+            ),
+            CryptoError::InvalidIvLength { expected, actual } => {
+                write!(f, "Invalid IV length {}, expected {}", actual, expected)
+            }
+            CryptoError::InvalidNonceLength { expected, actual } => {
+                write!(f, "Invalid nonce length {}, expected {}", actual, expected)
+            }
+            CryptoError::InvalidBlockSize(block_size) => {
+                write!(f, "Invalid block size {}", block_size)
+            }
+            CryptoError::NotBlockAligned { len, block_size } => write!(
+                f,
+                "Data of {} bytes is not a multiple of the block size {}",
+                len, block_size
+            ),
+            CryptoError::Padding(e) => write!(f, "{}", e),
+            CryptoError::Hex(e) => write!(f, "Invalid hex: {}", e),
+            CryptoError::Base64(e) => write!(f, "{}", e),
+            CryptoError::LengthMismatch { left, right } => write!(
+                f,
+                "The inputs should have the same length, got {} and {} bytes",
+                left, right
+            ),
+            CryptoError::EmptyInput => write!(f, "The input is empty"),
+            CryptoError::InputTooShort { min_len, len } => write!(
+                f,
+                "The input is too short, got {} bytes but needs at least {}",
+                len, min_len
+            ),
+            CryptoError::CounterOverflow => write!(f, "The counter would wrap around"),
+            CryptoError::IndexOutOfRange { index, len } => {
+                write!(f, "Index {} is out of range, there are {}", index, len)
+            }
+            CryptoError::Io { message, .. } => write!(f, "I/O error: {}", message),
+            CryptoError::InvalidModel(reason) => write!(f, "Invalid language model: {}", reason),
+            CryptoError::InvalidParameter(reason) => write!(f, "{}", reason),
+            CryptoError::InvalidRecipe { line, message } => {
+                write!(f, "Invalid recipe on line {}: {}", line, message)
+            }
+            CryptoError::RecipeStep {
+                step,
+                operation,
+                error,
+            } => write!(f, "Step {} ({}) failed: {}", step, operation, error),
+            CryptoError::WorkerFailed => write!(f, "A worker thread stopped unexpectedly"),
+        }
+    }
+}
+
+fn one_of(values: &[usize]) -> String {
+    match values {
+        [] => "nothing".to_string(),
+        [value] => value.to_string(),
+        [init @ .., last] => format!(
+            "{} or {}",
+            init.iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            last
+        ),
+    }
+}
+
+impl std::error::Error for CryptoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CryptoError::Padding(e) => Some(e),
+            CryptoError::Hex(e) => Some(e),
+            CryptoError::Base64(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<PaddingError> for CryptoError {
+    fn from(e: PaddingError) -> Self {
+        CryptoError::Padding(e)
+    }
+}
+
+/// For conversions that can't fail, so they fit where fallible ones are expected.
+impl From<std::convert::Infallible> for CryptoError {
+    fn from(never: std::convert::Infallible) -> Self {
+        match never {}
+    }
+}
+
+impl From<hex::FromHexError> for CryptoError {
+    fn from(e: hex::FromHexError) -> Self {
+        CryptoError::Hex(e)
+    }
+}
+
+impl From<Base64DecodeError> for CryptoError {
+    fn from(e: Base64DecodeError) -> Self {
+        CryptoError::Base64(e)
+    }
+}
+
+impl From<std::io::Error> for CryptoError {
+    fn from(e: std::io::Error) -> Self {
+        CryptoError::Io {
+            kind: e.kind(),
+            message: e.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let error = CryptoError::InvalidKeyLength {
+            expected: &[16, 24, 32],
+            actual: 7,
+        };
+        assert_eq!(
+            "Invalid key length 7, expected 16, 24 or 32",
+            error.to_string()
+        );
+        let error = CryptoError::from(PaddingError::ZeroPaddingLength);
+        assert_eq!("Padding length is zero", error.to_string());
+    }
+}
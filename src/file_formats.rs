@@ -0,0 +1,255 @@
+use crate::scoring::Scorer;
+use crate::set1::challenge6::KnownPlaintext;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Png,
+    Zip,
+    Elf,
+    Pdf,
+    Pe,
+    Gzip,
+    Zlib,
+}
+
+impl FileFormat {
+    pub const ALL: [FileFormat; 7] = [
+        FileFormat::Png,
+        FileFormat::Zip,
+        FileFormat::Elf,
+        FileFormat::Pdf,
+        FileFormat::Pe,
+        FileFormat::Gzip,
+        FileFormat::Zlib,
+    ];
+
+    /// The bytes every file of the format starts with.
+    pub fn magic(&self) -> &'static [u8] {
+        match self {
+            FileFormat::Png => b"\x89PNG\r\n\x1a\n",
+            FileFormat::Zip => b"PK\x03\x04",
+            FileFormat::Elf => b"\x7fELF",
+            FileFormat::Pdf => b"%PDF-",
+            FileFormat::Pe => b"MZ",
+            FileFormat::Gzip => b"\x1f\x8b\x08",
+            FileFormat::Zlib => b"\x78",
+        }
+    }
+
+    /// The magic as known plaintext, to pin the first key bytes when breaking an XOR'ed file
+    /// that is expected to be of this format.
+    pub fn known_plaintext(&self) -> KnownPlaintext<'static> {
+        KnownPlaintext {
+            offset: 0,
+            bytes: self.magic(),
+        }
+    }
+
+    /// Checks the structure beyond the magic, e.g. that the header fields have valid values.
+    pub fn has_valid_structure(&self, data: &[u8]) -> bool {
+        match self {
+            FileFormat::Png => data.get(8..16) == Some(b"\0\0\0\x0dIHDR"),
+            FileFormat::Zip => {
+                let tail = &data[data.len().saturating_sub(1024)..];
+                tail.windows(4).any(|w| w == b"PK\x05\x06")
+            }
+            FileFormat::Elf => match data.get(4..16) {
+                Some(ident) => {
+                    matches!(ident[0], 1 | 2)
+                        && matches!(ident[1], 1 | 2)
+                        && ident[2] == 1
+                        && ident[5..].iter().all(|b| *b == 0)
+                }
+                None => false,
+            },
+            FileFormat::Pdf => {
+                let tail = &data[data.len().saturating_sub(1024)..];
+                tail.windows(5).any(|w| w == b"%%EOF")
+            }
+            FileFormat::Pe => match data.get(0x3c..0x40) {
+                Some(offset) => {
+                    let offset = u32::from_le_bytes(offset.try_into().unwrap()) as usize;
+                    data.get(offset..offset.saturating_add(4)) == Some(b"PE\0\0")
+                }
+                None => false,
+            },
+            FileFormat::Gzip => data.get(3).is_some_and(|flags| flags & 0xe0 == 0),
+            FileFormat::Zlib => match data {
+                [cmf, flg, ..] => (*cmf as u16 * 256 + *flg as u16).is_multiple_of(31),
+                _ => false,
+            },
+        }
+    }
+
+    /// The format whose magic and structure `data` matches best, if any.
+    pub fn detect(data: &[u8]) -> Option<FileFormat> {
+        FileFormat::ALL
+            .iter()
+            .copied()
+            .filter(|format| format.match_score(data) > 0.0)
+            .max_by(|l, r| l.match_score(data).total_cmp(&r.match_score(data)))
+    }
+
+    /// Between 0 for no match and 1 for a matching magic and structure. Longer magics count
+    /// for more, since they are less likely to match by chance.
+    fn match_score(&self, data: &[u8]) -> f64 {
+        if !data.starts_with(self.magic()) {
+            return 0.0;
+        }
+        let magic = self.magic().len().min(8) as f64 / 16.0;
+        if self.has_valid_structure(data) {
+            magic + 0.5
+        } else {
+            magic
+        }
+    }
+}
+
+/// Scores binary data, which unlike text is dominated by zero bytes, often in long runs, and
+/// usually starts with a known header. Only the formats in `formats` are recognised.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BinaryScorer<'a> {
+    pub zero_weight: f64,
+    pub zero_run_weight: f64,
+    pub format_weight: f64,
+    pub formats: &'a [FileFormat],
+}
+
+impl BinaryScorer<'static> {
+    pub const DEFAULT: BinaryScorer<'static> = BinaryScorer {
+        zero_weight: 1.0,
+        zero_run_weight: 1.0,
+        format_weight: 2.0,
+        formats: &FileFormat::ALL,
+    };
+
+    /// For the transposed columns of a repeating key XOR ciphertext, where neither runs nor
+    /// headers survive.
+    pub const COLUMNS: BinaryScorer<'static> = BinaryScorer {
+        zero_weight: 1.0,
+        zero_run_weight: 0.0,
+        format_weight: 0.0,
+        formats: &[],
+    };
+}
+
+impl Default for BinaryScorer<'static> {
+    fn default() -> Self {
+        BinaryScorer::DEFAULT
+    }
+}
+
+impl Scorer for BinaryScorer<'_> {
+    fn score(&self, candidate: &[u8]) -> f64 {
+        if candidate.is_empty() {
+            return 0.0;
+        }
+        let mut score = self.zero_weight * zero_fraction(candidate);
+        if self.zero_run_weight != 0.0 {
+            score += self.zero_run_weight * zero_run_fraction(candidate, MIN_ZERO_RUN);
+        }
+        if self.format_weight != 0.0 {
+            let format = self
+                .formats
+                .iter()
+                .map(|format| format.match_score(candidate))
+                .fold(0.0, f64::max);
+            score += self.format_weight * format;
+        }
+        score
+    }
+}
+
+const MIN_ZERO_RUN: usize = 4;
+
+pub fn zero_fraction(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    data.iter().filter(|b| **b == 0).count() as f64 / data.len() as f64
+}
+
+/// The fraction of `data` in runs of at least `min_len` zero bytes.
+pub fn zero_run_fraction(data: &[u8], min_len: usize) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let in_runs: usize = data
+        .split(|b| *b != 0)
+        .map(|run| run.len())
+        .filter(|len| *len >= min_len.max(1))
+        .sum();
+    in_runs as f64 / data.len() as f64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::{Ciphertext, Key, Plaintext};
+    use crate::set1::challenge5::repeating_key_xor_encrypt;
+    use crate::set1::challenge6::{rank_repeating_key_xor, RepeatingKeyXorOptions};
+
+    /// Something shaped like a small ELF executable: a header, zero padding and code.
+    fn elf_file() -> Vec<u8> {
+        let mut data = b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0\x02\0\x3e\0\x01\0\0\0".to_vec();
+        let mut state = 0x2545f491u32;
+        for section in 0..12 {
+            data.resize(data.len() + 40 + section * 7, 0);
+            for _ in 0..120 {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                data.push(state as u8);
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Some(FileFormat::Elf), FileFormat::detect(&elf_file()));
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x10";
+        assert_eq!(Some(FileFormat::Png), FileFormat::detect(png));
+        let zlib = [0x78, 0x9c, 0x4b, 0x4c];
+        assert_eq!(Some(FileFormat::Zlib), FileFormat::detect(&zlib));
+        assert!(FileFormat::Zlib.has_valid_structure(&zlib));
+        assert!(!FileFormat::Zlib.has_valid_structure(&[0x78, 0x9d]));
+        assert_eq!(None, FileFormat::detect(b"Hello"));
+    }
+
+    #[test]
+    fn test_pe_structure() {
+        let mut pe = vec![0; 0x80];
+        pe[..2].copy_from_slice(b"MZ");
+        pe[0x3c] = 0x40;
+        pe[0x40..0x44].copy_from_slice(b"PE\0\0");
+        assert!(FileFormat::Pe.has_valid_structure(&pe));
+        pe[0x3c] = 0xff;
+        assert!(!FileFormat::Pe.has_valid_structure(&pe));
+    }
+
+    #[test]
+    fn test_zero_runs() {
+        assert_eq!(0.5, zero_fraction(&[0, 1, 0, 1]));
+        assert_eq!(0.5, zero_run_fraction(&[0, 0, 0, 0, 1, 0, 0, 1], 4));
+    }
+
+    #[test]
+    fn test_break_xored_binary() {
+        let plaintext = Plaintext(elf_file());
+        let key = Key(b"x0r!k3y".to_vec());
+        let ciphertext: Ciphertext = repeating_key_xor_encrypt(&plaintext, &key).unwrap();
+        let options = RepeatingKeyXorOptions {
+            column_scorer: &BinaryScorer::COLUMNS,
+            plaintext_scorer: &BinaryScorer::DEFAULT,
+            ..RepeatingKeyXorOptions::default()
+        };
+        let candidates = rank_repeating_key_xor(&ciphertext, &options).unwrap();
+        assert_eq!(key, candidates[0].key);
+        assert_eq!(plaintext, candidates[0].plaintext);
+        assert_eq!(
+            Some(FileFormat::Elf),
+            FileFormat::detect(&candidates[0].plaintext.0)
+        );
+    }
+}
@@ -0,0 +1,443 @@
+use crate::error::CryptoError;
+use crate::modes::BlockCipher;
+
+pub type Block = [u8; 16];
+
+/// AES as described in FIPS-197, written from scratch for learning purposes. The state is
+/// stored column by column, so a block can be used as the state without any reordering.
+pub struct HomebrewAes {
+    round_keys: Vec<Block>,
+}
+
+/// The state at each step of one round, named after the rows of the FIPS-197 appendix C
+/// tables. The last round has no `MixColumns`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RoundTrace {
+    pub round: usize,
+    pub start: Block,
+    pub after_sub_bytes: Block,
+    pub after_shift_rows: Block,
+    pub after_mix_columns: Option<Block>,
+    pub round_key: Block,
+}
+
+impl HomebrewAes {
+    pub fn new(key: &[u8]) -> Result<Self, CryptoError> {
+        if !matches!(key.len(), 16 | 24 | 32) {
+            return Err(CryptoError::InvalidKeyLength {
+                expected: &[16, 24, 32],
+                actual: key.len(),
+            });
+        }
+        Ok(HomebrewAes {
+            round_keys: key_schedule(key),
+        })
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.round_keys.len() - 1
+    }
+
+    pub fn round_keys(&self) -> &[Block] {
+        &self.round_keys
+    }
+
+    pub fn encrypt(&self, block: &mut Block) {
+        add_round_key(block, &self.round_keys[0]);
+        for round in 1..self.rounds() {
+            sub_bytes(block);
+            shift_rows(block);
+            mix_columns(block);
+            add_round_key(block, &self.round_keys[round]);
+        }
+        sub_bytes(block);
+        shift_rows(block);
+        add_round_key(block, &self.round_keys[self.rounds()]);
+    }
+
+    /// Encrypts `block` in place and returns the state after every step of every round.
+    pub fn encrypt_traced(&self, block: &mut Block) -> Vec<RoundTrace> {
+        let mut trace = vec![];
+        add_round_key(block, &self.round_keys[0]);
+        for round in 1..=self.rounds() {
+            let start = *block;
+            sub_bytes(block);
+            let after_sub_bytes = *block;
+            shift_rows(block);
+            let after_shift_rows = *block;
+            let after_mix_columns = if round != self.rounds() {
+                mix_columns(block);
+                Some(*block)
+            } else {
+                None
+            };
+            add_round_key(block, &self.round_keys[round]);
+            trace.push(RoundTrace {
+                round,
+                start,
+                after_sub_bytes,
+                after_shift_rows,
+                after_mix_columns,
+                round_key: self.round_keys[round],
+            });
+        }
+        trace
+    }
+
+    pub fn decrypt(&self, block: &mut Block) {
+        add_round_key(block, &self.round_keys[self.rounds()]);
+        for round in (1..self.rounds()).rev() {
+            inv_shift_rows(block);
+            inv_sub_bytes(block);
+            add_round_key(block, &self.round_keys[round]);
+            inv_mix_columns(block);
+        }
+        inv_shift_rows(block);
+        inv_sub_bytes(block);
+        add_round_key(block, &self.round_keys[0]);
+    }
+}
+
+macro_rules! homebrew_aes_with_key_size {
+    ($name:ident, $key_size:expr) => {
+        pub struct $name(HomebrewAes);
+
+        impl $name {
+            pub fn inner(&self) -> &HomebrewAes {
+                &self.0
+            }
+        }
+
+        impl BlockCipher for $name {
+            const BLOCK_SIZE: usize = 16;
+            const KEY_SIZE: usize = $key_size;
+
+            fn with_key(key: &[u8]) -> Result<Self, CryptoError> {
+                if key.len() != $key_size {
+                    return Err(CryptoError::InvalidKeyLength {
+                        expected: &[$key_size],
+                        actual: key.len(),
+                    });
+                }
+                Ok($name(HomebrewAes::new(key)?))
+            }
+
+            fn encrypt_block(&self, block: &mut [u8]) {
+                let mut state: Block = (&*block).try_into().unwrap();
+                self.0.encrypt(&mut state);
+                block.copy_from_slice(&state);
+            }
+
+            fn decrypt_block(&self, block: &mut [u8]) {
+                let mut state: Block = (&*block).try_into().unwrap();
+                self.0.decrypt(&mut state);
+                block.copy_from_slice(&state);
+            }
+        }
+    };
+}
+
+homebrew_aes_with_key_size!(HomebrewAes128, 16);
+homebrew_aes_with_key_size!(HomebrewAes192, 24);
+homebrew_aes_with_key_size!(HomebrewAes256, 32);
+
+/// Expands a 16, 24 or 32 byte key into the 11, 13 or 15 round keys.
+pub fn key_schedule(key: &[u8]) -> Vec<Block> {
+    let nk = key.len() / 4;
+    let rounds = nk + 6;
+    let mut words: Vec<[u8; 4]> = key
+        .chunks_exact(4)
+        .map(|w| [w[0], w[1], w[2], w[3]])
+        .collect();
+    let mut rcon = 1u8;
+    for i in nk..4 * (rounds + 1) {
+        let mut word = words[i - 1];
+        if i % nk == 0 {
+            word.rotate_left(1);
+            word.iter_mut().for_each(|b| *b = SBOX[*b as usize]);
+            word[0] ^= rcon;
+            rcon = xtime(rcon);
+        } else if nk > 6 && i % nk == 4 {
+            word.iter_mut().for_each(|b| *b = SBOX[*b as usize]);
+        }
+        let previous = words[i - nk];
+        words.push([
+            word[0] ^ previous[0],
+            word[1] ^ previous[1],
+            word[2] ^ previous[2],
+            word[3] ^ previous[3],
+        ]);
+    }
+    words
+        .chunks_exact(4)
+        .map(|w| {
+            let mut round_key = [0; 16];
+            for (column, word) in w.iter().enumerate() {
+                round_key[4 * column..4 * column + 4].copy_from_slice(word);
+            }
+            round_key
+        })
+        .collect()
+}
+
+pub fn add_round_key(state: &mut Block, round_key: &Block) {
+    state.iter_mut().zip(round_key).for_each(|(s, k)| *s ^= k);
+}
+
+pub fn sub_bytes(state: &mut Block) {
+    state.iter_mut().for_each(|b| *b = SBOX[*b as usize]);
+}
+
+pub fn inv_sub_bytes(state: &mut Block) {
+    state.iter_mut().for_each(|b| *b = INV_SBOX[*b as usize]);
+}
+
+/// Row `r` is rotated left by `r` columns.
+pub fn shift_rows(state: &mut Block) {
+    let old = *state;
+    for column in 0..4 {
+        for row in 0..4 {
+            state[4 * column + row] = old[4 * ((column + row) % 4) + row];
+        }
+    }
+}
+
+pub fn inv_shift_rows(state: &mut Block) {
+    let old = *state;
+    for column in 0..4 {
+        for row in 0..4 {
+            state[4 * ((column + row) % 4) + row] = old[4 * column + row];
+        }
+    }
+}
+
+pub fn mix_columns(state: &mut Block) {
+    for column in state.chunks_exact_mut(4) {
+        let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+        column[0] = gf_mul(a0, 2) ^ gf_mul(a1, 3) ^ a2 ^ a3;
+        column[1] = a0 ^ gf_mul(a1, 2) ^ gf_mul(a2, 3) ^ a3;
+        column[2] = a0 ^ a1 ^ gf_mul(a2, 2) ^ gf_mul(a3, 3);
+        column[3] = gf_mul(a0, 3) ^ a1 ^ a2 ^ gf_mul(a3, 2);
+    }
+}
+
+pub fn inv_mix_columns(state: &mut Block) {
+    for column in state.chunks_exact_mut(4) {
+        let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+        column[0] = gf_mul(a0, 14) ^ gf_mul(a1, 11) ^ gf_mul(a2, 13) ^ gf_mul(a3, 9);
+        column[1] = gf_mul(a0, 9) ^ gf_mul(a1, 14) ^ gf_mul(a2, 11) ^ gf_mul(a3, 13);
+        column[2] = gf_mul(a0, 13) ^ gf_mul(a1, 9) ^ gf_mul(a2, 14) ^ gf_mul(a3, 11);
+        column[3] = gf_mul(a0, 11) ^ gf_mul(a1, 13) ^ gf_mul(a2, 9) ^ gf_mul(a3, 14);
+    }
+}
+
+const fn xtime(a: u8) -> u8 {
+    (a << 1) ^ if a & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+/// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1.
+pub const fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+    product
+}
+
+const fn gf_inverse(a: u8) -> u8 {
+    // a^254 == a^-1 in GF(2^8), and it maps 0 to 0 as the S-box wants.
+    let mut result = 1;
+    let mut i = 0;
+    while i < 254 {
+        result = gf_mul(result, a);
+        i += 1;
+    }
+    result
+}
+
+const fn build_sbox() -> [u8; 256] {
+    let mut sbox = [0; 256];
+    let mut x = 0;
+    while x < 256 {
+        let b = gf_inverse(x as u8);
+        sbox[x] =
+            b ^ b.rotate_left(1) ^ b.rotate_left(2) ^ b.rotate_left(3) ^ b.rotate_left(4) ^ 0x63;
+        x += 1;
+    }
+    sbox
+}
+
+const fn invert_sbox(sbox: &[u8; 256]) -> [u8; 256] {
+    let mut inverse = [0; 256];
+    let mut x = 0;
+    while x < 256 {
+        inverse[sbox[x] as usize] = x as u8;
+        x += 1;
+    }
+    inverse
+}
+
+pub static SBOX: [u8; 256] = build_sbox();
+pub static INV_SBOX: [u8; 256] = invert_sbox(&SBOX);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::{AesKey, Ciphertext, Iv, Plaintext};
+    use crate::modes::{cbc_decrypt, ecb_encrypt};
+    use crate::set1::challenge1::{base64_decode_homebrew, Base64Config};
+    use crate::set2::challenge9::Padding;
+    use aes::{Aes128, Aes192, Aes256};
+    use rand::Rng;
+    use std::str::FromStr;
+
+    const FIPS197_PLAINTEXT: &str = "00112233445566778899aabbccddeeff";
+
+    fn block(hex_string: &str) -> Block {
+        hex::decode(hex_string).unwrap().try_into().unwrap()
+    }
+
+    fn check_fips197_vector(key_hex: &str, expected_hex: &str) {
+        let aes = HomebrewAes::new(&hex::decode(key_hex).unwrap()).unwrap();
+        let mut state = block(FIPS197_PLAINTEXT);
+        aes.encrypt(&mut state);
+        assert_eq!(expected_hex, hex::encode(state));
+        aes.decrypt(&mut state);
+        assert_eq!(FIPS197_PLAINTEXT, hex::encode(state));
+    }
+
+    #[test]
+    fn test_sbox() {
+        assert_eq!(0x63, SBOX[0x00]);
+        assert_eq!(0xed, SBOX[0x53]);
+        assert_eq!(0x16, SBOX[0xff]);
+        assert_eq!(0x53, INV_SBOX[0xed]);
+    }
+
+    #[test]
+    fn test_key_schedule_fips197_appendix_a1() {
+        let round_keys = key_schedule(&hex::decode("2b7e151628aed2a6abf7158809cf4f3c").unwrap());
+        assert_eq!(11, round_keys.len());
+        assert_eq!(
+            "a0fafe1788542cb123a339392a6c7605",
+            hex::encode(round_keys[1])
+        );
+        assert_eq!(
+            "d014f9a8c9ee2589e13f0cc8b6630ca6",
+            hex::encode(round_keys[10])
+        );
+    }
+
+    #[test]
+    fn test_fips197_appendix_c() {
+        check_fips197_vector(
+            "000102030405060708090a0b0c0d0e0f",
+            "69c4e0d86a7b0430d8cdb78070b4c55a",
+        );
+        check_fips197_vector(
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "dda97ca4864cdfe06eaf70a0ec0d7191",
+        );
+        check_fips197_vector(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "8ea2b7ca516745bfeafc49904b496089",
+        );
+    }
+
+    #[test]
+    fn test_trace_fips197_appendix_c1() {
+        let aes =
+            HomebrewAes::new(&hex::decode("000102030405060708090a0b0c0d0e0f").unwrap()).unwrap();
+        let mut state = block(FIPS197_PLAINTEXT);
+        let trace = aes.encrypt_traced(&mut state);
+        assert_eq!(10, trace.len());
+        let first = &trace[0];
+        assert_eq!("00102030405060708090a0b0c0d0e0f0", hex::encode(first.start));
+        assert_eq!(
+            "63cab7040953d051cd60e0e7ba70e18c",
+            hex::encode(first.after_sub_bytes)
+        );
+        assert_eq!(
+            "6353e08c0960e104cd70b751bacad0e7",
+            hex::encode(first.after_shift_rows)
+        );
+        assert_eq!(
+            "5f72641557f5bc92f7be3b291db9f91a",
+            hex::encode(first.after_mix_columns.unwrap())
+        );
+        assert_eq!(
+            "d6aa74fdd2af72fadaa678f1d6ab76fe",
+            hex::encode(first.round_key)
+        );
+        assert_eq!(None, trace[9].after_mix_columns);
+    }
+
+    #[test]
+    fn test_trace_matches_encrypt() {
+        let aes = HomebrewAes::new(&[9; 32]).unwrap();
+        let mut traced = block(FIPS197_PLAINTEXT);
+        aes.encrypt_traced(&mut traced);
+        let mut plain = block(FIPS197_PLAINTEXT);
+        aes.encrypt(&mut plain);
+        assert_eq!(plain, traced);
+    }
+
+    #[test]
+    fn test_against_aes_crate() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let plaintext = Plaintext((0..rng.gen_range(0..100)).map(|_| rng.gen()).collect());
+            let key = AesKey::aes128(&rng.gen::<[u8; 16]>()).unwrap();
+            assert_eq!(
+                ecb_encrypt::<Aes128>(&plaintext, &key, Padding::Pkcs7)
+                    .unwrap()
+                    .0,
+                ecb_encrypt::<HomebrewAes128>(&plaintext, &key, Padding::Pkcs7)
+                    .unwrap()
+                    .0
+            );
+            let key = AesKey::aes192(&rng.gen::<[u8; 24]>()).unwrap();
+            assert_eq!(
+                ecb_encrypt::<Aes192>(&plaintext, &key, Padding::Pkcs7)
+                    .unwrap()
+                    .0,
+                ecb_encrypt::<HomebrewAes192>(&plaintext, &key, Padding::Pkcs7)
+                    .unwrap()
+                    .0
+            );
+            let key = AesKey::aes256(&rng.gen::<[u8; 32]>()).unwrap();
+            assert_eq!(
+                ecb_encrypt::<Aes256>(&plaintext, &key, Padding::Pkcs7)
+                    .unwrap()
+                    .0,
+                ecb_encrypt::<HomebrewAes256>(&plaintext, &key, Padding::Pkcs7)
+                    .unwrap()
+                    .0
+            );
+        }
+    }
+
+    #[test]
+    fn test_cbc_decrypt_challenge10() {
+        let ciphertext = Ciphertext(
+            base64_decode_homebrew(include_str!("set2/10.txt"), Base64Config::MIME).unwrap(),
+        );
+        let key = AesKey::from_str("YELLOW SUBMARINE").unwrap();
+        let iv = Iv::block(&[0; 16]).unwrap();
+        let plaintext =
+            cbc_decrypt::<HomebrewAes128>(&ciphertext, &key, &iv, Padding::Pkcs7).unwrap();
+        assert!(plaintext
+            .to_string()
+            .contains("Play that funky music, white boy"));
+    }
+
+    #[test]
+    fn test_wrong_key_length() {
+        assert!(HomebrewAes::new(&[0; 20]).is_err());
+        assert!(HomebrewAes128::with_key(&[0; 24]).is_err());
+    }
+}
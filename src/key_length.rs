@@ -0,0 +1,283 @@
+use crate::bits::hamming_distance;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+/// Guesses the length of the key of a repeating key XOR ciphertext.
+pub trait KeyLengthEstimator: Sync {
+    /// The lengths in `key_lengths`, most likely first. Lengths the estimator has nothing to say
+    /// about, e.g. because the ciphertext is too short, are left out.
+    fn rank(&self, ciphertext: &[u8], key_lengths: RangeInclusive<usize>) -> Vec<usize>;
+}
+
+/// Compares the first `samples` key length sized chunks with each other. The right key length
+/// gives the lowest normalized Hamming distance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HammingDistance {
+    pub samples: usize,
+}
+
+impl HammingDistance {
+    pub const DEFAULT: HammingDistance = HammingDistance { samples: 10 };
+}
+
+impl KeyLengthEstimator for HammingDistance {
+    fn rank(&self, ciphertext: &[u8], key_lengths: RangeInclusive<usize>) -> Vec<usize> {
+        // A single chunk has nothing to be compared with.
+        if ciphertext.is_empty() || self.samples < 2 {
+            return vec![];
+        }
+        let mut ks_scores: Vec<_> = key_lengths
+            .filter(|ks| *ks > 0)
+            .filter_map(|ks| {
+                let v: Vec<_> = ciphertext.chunks(ks).take(self.samples).collect();
+                let hamming_values = multi_hamming(&v);
+                if hamming_values.is_empty() {
+                    None
+                } else {
+                    let mh: usize = hamming_values.iter().sum::<usize>() / hamming_values.len();
+                    Some((ks, mh))
+                }
+            })
+            .collect();
+
+        ks_scores.sort_by_key(|(_ks, score)| *score);
+        ks_scores.iter().map(|x| x.0).collect()
+    }
+}
+
+/// Splits the ciphertext into columns for each key length. Under the right key length every
+/// column is single byte XOR'ed text, which keeps the index of coincidence of the plaintext.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexOfCoincidence;
+
+impl KeyLengthEstimator for IndexOfCoincidence {
+    fn rank(&self, ciphertext: &[u8], key_lengths: RangeInclusive<usize>) -> Vec<usize> {
+        let scores = key_lengths
+            .filter(|ks| *ks > 0 && ciphertext.len() >= 2 * ks)
+            .map(|ks| {
+                let columns = columns(ciphertext, ks);
+                let sum: f64 = columns.iter().map(|c| index_of_coincidence(c)).sum();
+                (ks, sum / columns.len() as f64)
+            })
+            .collect();
+        by_score(scores)
+    }
+}
+
+/// Finds repeated n-grams, which are likely the same plaintext under the same part of the key,
+/// and votes for the key lengths that divide the distances between them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Kasiski {
+    pub ngram_len: usize,
+}
+
+impl Kasiski {
+    pub const DEFAULT: Kasiski = Kasiski { ngram_len: 3 };
+}
+
+impl KeyLengthEstimator for Kasiski {
+    fn rank(&self, ciphertext: &[u8], key_lengths: RangeInclusive<usize>) -> Vec<usize> {
+        if self.ngram_len == 0 {
+            return vec![];
+        }
+        let mut last_seen = HashMap::new();
+        let mut distances = vec![];
+        for (offset, ngram) in ciphertext.windows(self.ngram_len).enumerate() {
+            if let Some(previous) = last_seen.insert(ngram, offset) {
+                distances.push(offset - previous);
+            }
+        }
+        if distances.is_empty() {
+            return vec![];
+        }
+        // A random distance is divisible by ks with probability 1/ks, so the votes are scaled by
+        // ks to not favour the short lengths.
+        let scores = key_lengths
+            .filter(|ks| *ks > 0)
+            .map(|ks| {
+                let votes = distances.iter().filter(|d| d.is_multiple_of(ks)).count();
+                (ks, (votes * ks) as f64 / distances.len() as f64)
+            })
+            .collect();
+        by_score(scores)
+    }
+}
+
+/// Estimates the key length from the index of coincidence of the whole ciphertext, which
+/// drops from `text_coincidence` towards `random_coincidence` as the key gets longer. This is
+/// only a rough estimate, the lengths closest to it are ranked first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Friedman {
+    /// The index of coincidence of the plaintext.
+    pub text_coincidence: f64,
+    /// The index of coincidence between plaintext bytes XOR'ed with different key bytes.
+    pub random_coincidence: f64,
+}
+
+impl Friedman {
+    /// For English text and a key of printable characters.
+    pub const ENGLISH: Friedman = Friedman {
+        text_coincidence: 0.066,
+        random_coincidence: 0.0167,
+    };
+
+    pub fn estimate(&self, ciphertext: &[u8]) -> Option<f64> {
+        if ciphertext.len() < 2 {
+            return None;
+        }
+        let coincidence = index_of_coincidence(ciphertext);
+        if coincidence <= self.random_coincidence {
+            return None;
+        }
+        Some(
+            (self.text_coincidence - self.random_coincidence)
+                / (coincidence - self.random_coincidence),
+        )
+    }
+}
+
+impl KeyLengthEstimator for Friedman {
+    fn rank(&self, ciphertext: &[u8], key_lengths: RangeInclusive<usize>) -> Vec<usize> {
+        match self.estimate(ciphertext) {
+            Some(estimate) => by_score(
+                key_lengths
+                    .map(|ks| (ks, -(ks as f64 - estimate).abs()))
+                    .collect(),
+            ),
+            None => vec![],
+        }
+    }
+}
+
+/// Lets several estimators vote on the key length. Each ranking gives a length as many points
+/// as there are lengths ranked below it.
+#[derive(Clone, Copy)]
+pub struct Combined<'a> {
+    pub estimators: &'a [&'a dyn KeyLengthEstimator],
+}
+
+impl Combined<'static> {
+    pub const ALL: Combined<'static> = Combined {
+        estimators: &[
+            &HammingDistance::DEFAULT,
+            &IndexOfCoincidence,
+            &Kasiski::DEFAULT,
+            &Friedman::ENGLISH,
+        ],
+    };
+}
+
+impl KeyLengthEstimator for Combined<'_> {
+    fn rank(&self, ciphertext: &[u8], key_lengths: RangeInclusive<usize>) -> Vec<usize> {
+        let mut points: Vec<(usize, f64)> = key_lengths.clone().map(|ks| (ks, 0.0)).collect();
+        let mut ranked_by_any = vec![false; points.len()];
+        for estimator in self.estimators {
+            let ranking = estimator.rank(ciphertext, key_lengths.clone());
+            for (position, ks) in ranking.iter().enumerate() {
+                if let Some(index) = ks.checked_sub(*key_lengths.start()) {
+                    if let Some((_ks, points)) = points.get_mut(index) {
+                        *points += (ranking.len() - position) as f64;
+                        ranked_by_any[index] = true;
+                    }
+                }
+            }
+        }
+        let points = points
+            .into_iter()
+            .zip(ranked_by_any)
+            .filter(|(_points, ranked)| *ranked)
+            .map(|(points, _ranked)| points)
+            .collect();
+        by_score(points)
+    }
+}
+
+/// The lengths sorted by score, highest first. Ties keep the shorter length first.
+fn by_score(mut scores: Vec<(usize, f64)>) -> Vec<usize> {
+    scores.sort_by(|(_l, l), (_r, r)| r.total_cmp(l));
+    scores.into_iter().map(|(ks, _score)| ks).collect()
+}
+
+fn columns(data: &[u8], count: usize) -> Vec<Vec<u8>> {
+    let mut result = vec![vec![]; count];
+    for (index, byte) in data.iter().enumerate() {
+        result[index % count].push(*byte);
+    }
+    result
+}
+
+/// The probability that two bytes picked at different positions of `data` are equal.
+pub fn index_of_coincidence(data: &[u8]) -> f64 {
+    if data.len() < 2 {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for byte in data {
+        counts[*byte as usize] += 1;
+    }
+    let pairs: usize = counts.iter().map(|c| c * c.saturating_sub(1)).sum();
+    pairs as f64 / (data.len() * (data.len() - 1)) as f64
+}
+
+fn multi_hamming(data: &[&[u8]]) -> Vec<usize> {
+    let len = data[0].len();
+    let mut data = data.iter();
+    let base = data.next().unwrap();
+    data.filter(|d| d.len() == len)
+        .map(|d| hamming_distance(base, d) * 100 / len)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::set1::challenge1::{base64_decode_homebrew, Base64Config};
+
+    fn challenge6_ciphertext() -> Vec<u8> {
+        base64_decode_homebrew(include_str!("set1/6.txt"), Base64Config::MIME).unwrap()
+    }
+
+    #[test]
+    fn test_index_of_coincidence() {
+        assert_eq!(0.0, index_of_coincidence(b"abcd"));
+        assert_eq!(1.0, index_of_coincidence(b"aaaa"));
+        assert_eq!(0.0, index_of_coincidence(b"a"));
+    }
+
+    #[test]
+    fn test_estimators_find_challenge6_key_length() {
+        let ciphertext = challenge6_ciphertext();
+        let estimators: [&dyn KeyLengthEstimator; 3] =
+            [&IndexOfCoincidence, &Kasiski::DEFAULT, &Combined::ALL];
+        for estimator in estimators {
+            let ranking = estimator.rank(&ciphertext, 2..=40);
+            assert_eq!(29, ranking[0]);
+        }
+    }
+
+    #[test]
+    fn test_friedman_estimate() {
+        let estimate = Friedman::ENGLISH
+            .estimate(&challenge6_ciphertext())
+            .unwrap();
+        assert!((20.0..40.0).contains(&estimate));
+        assert_eq!(None, Friedman::ENGLISH.estimate(b"x"));
+    }
+
+    #[test]
+    fn test_short_ciphertext() {
+        assert!(IndexOfCoincidence.rank(b"abc", 2..=10).is_empty());
+        assert!(Kasiski::DEFAULT.rank(b"abc", 2..=10).is_empty());
+        assert!(Combined::ALL.rank(b"", 2..=10).is_empty());
+    }
+
+    #[test]
+    fn test_hamming_distance_samples() {
+        let ciphertext = challenge6_ciphertext();
+        for samples in [0, 1] {
+            assert!(HammingDistance { samples }.rank(&ciphertext, 2..=40).is_empty());
+        }
+        assert_eq!(vec![3], HammingDistance::DEFAULT.rank(b"abcabd", 3..=3));
+        assert!(HammingDistance::DEFAULT.rank(b"abc", 4..=4).is_empty());
+    }
+}
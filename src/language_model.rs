@@ -0,0 +1,322 @@
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::error::CryptoError;
+use crate::scoring::Scorer;
+
+/// Byte unigram and bigram frequencies of a language, trained from a corpus. Works on raw
+/// bytes, so it also models UTF-8 text such as German or Danish, and source code.
+#[derive(Clone, PartialEq)]
+pub struct LanguageModel {
+    unigram_counts: Vec<u64>,
+    bigram_counts: Vec<u64>,
+    unigram_log_probabilities: Vec<f64>,
+    bigram_log_probabilities: Vec<f64>,
+}
+
+const MAGIC: &[u8] = b"CPLM\x01";
+
+/// Added to every unigram count so bytes missing from the corpus aren't impossible.
+const UNIGRAM_SMOOTHING: f64 = 0.01;
+
+/// How many observations the unigram probabilities are worth when estimating the bigram
+/// probabilities, which are pulled towards them for rarely seen first bytes.
+const BIGRAM_SMOOTHING: f64 = 2.0;
+
+impl LanguageModel {
+    pub fn train(corpus: &[u8]) -> Result<LanguageModel, CryptoError> {
+        if corpus.is_empty() {
+            return Err(CryptoError::EmptyInput);
+        }
+        let mut unigram_counts = vec![0; 256];
+        let mut bigram_counts = vec![0; 256 * 256];
+        for byte in corpus {
+            unigram_counts[*byte as usize] += 1;
+        }
+        for pair in corpus.windows(2) {
+            bigram_counts[bigram_index(pair[0], pair[1])] += 1;
+        }
+        Ok(LanguageModel::from_counts(unigram_counts, bigram_counts))
+    }
+
+    /// A model of English prose, trained from a small bundled corpus.
+    pub fn english() -> &'static LanguageModel {
+        static ENGLISH: OnceLock<LanguageModel> = OnceLock::new();
+        ENGLISH.get_or_init(|| {
+            LanguageModel::train(include_bytes!("english.txt")).expect("the corpus isn't empty")
+        })
+    }
+
+    /// The unigram part of `english`, for scoring the columns of a repeating key XOR ciphertext.
+    pub fn english_unigrams() -> &'static Unigrams<'static> {
+        static ENGLISH_UNIGRAMS: OnceLock<Unigrams<'static>> = OnceLock::new();
+        ENGLISH_UNIGRAMS.get_or_init(|| Unigrams(LanguageModel::english()))
+    }
+
+    fn from_counts(unigram_counts: Vec<u64>, bigram_counts: Vec<u64>) -> LanguageModel {
+        let total: f64 = unigram_counts.iter().map(|c| *c as f64).sum();
+        let unigram_probabilities: Vec<f64> = unigram_counts
+            .iter()
+            .map(|c| (*c as f64 + UNIGRAM_SMOOTHING) / (total + 256.0 * UNIGRAM_SMOOTHING))
+            .collect();
+        let mut bigram_log_probabilities = vec![0.0; 256 * 256];
+        for first in 0..=255 {
+            let row = &bigram_counts[bigram_index(first, 0)..=bigram_index(first, 255)];
+            let row_total: f64 = row.iter().map(|c| *c as f64).sum();
+            for (second, count) in row.iter().enumerate() {
+                let p = (*count as f64 + BIGRAM_SMOOTHING * unigram_probabilities[second])
+                    / (row_total + BIGRAM_SMOOTHING);
+                bigram_log_probabilities[bigram_index(first, second as u8)] = p.log10();
+            }
+        }
+        LanguageModel {
+            unigram_counts,
+            bigram_counts,
+            unigram_log_probabilities: unigram_probabilities.iter().map(|p| p.log10()).collect(),
+            bigram_log_probabilities,
+        }
+    }
+
+    /// The average log10 probability of the bytes in `text`, each on its own.
+    pub fn unigram_log_probability(&self, text: &[u8]) -> Option<f64> {
+        if text.is_empty() {
+            return None;
+        }
+        let sum: f64 = text
+            .iter()
+            .map(|b| self.unigram_log_probabilities[*b as usize])
+            .sum();
+        Some(sum / text.len() as f64)
+    }
+
+    /// The average log10 probability per byte of `text`, each byte given the one before it.
+    pub fn log_probability(&self, text: &[u8]) -> Option<f64> {
+        let first = *text.first()?;
+        let sum: f64 = text
+            .windows(2)
+            .map(|pair| self.bigram_log_probabilities[bigram_index(pair[0], pair[1])])
+            .sum::<f64>()
+            + self.unigram_log_probabilities[first as usize];
+        Some(sum / text.len() as f64)
+    }
+
+    /// Serializes the counts the model was trained with. Only the bytes and pairs that occurred
+    /// are stored, with variable length counts, so models of text stay small.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        write_sparse(&mut bytes, &self.unigram_counts, |index, out| {
+            out.push(index as u8)
+        });
+        write_sparse(&mut bytes, &self.bigram_counts, |index, out| {
+            out.extend_from_slice(&(index as u16).to_be_bytes())
+        });
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<LanguageModel, CryptoError> {
+        let mut reader = Reader(
+            bytes
+                .strip_prefix(MAGIC)
+                .ok_or(CryptoError::InvalidModel("unknown file format"))?,
+        );
+        let mut unigram_counts = vec![0; 256];
+        for _ in 0..reader.varint()? {
+            let index = reader.bytes(1)?[0] as usize;
+            unigram_counts[index] = reader.varint()?;
+        }
+        let mut bigram_counts = vec![0; 256 * 256];
+        for _ in 0..reader.varint()? {
+            let index = reader.bytes(2)?;
+            let index = u16::from_be_bytes([index[0], index[1]]) as usize;
+            bigram_counts[index] = reader.varint()?;
+        }
+        if !reader.0.is_empty() {
+            return Err(CryptoError::InvalidModel("trailing data"));
+        }
+        if unigram_counts.iter().all(|c| *c == 0) {
+            return Err(CryptoError::InvalidModel("no unigrams"));
+        }
+        Ok(LanguageModel::from_counts(unigram_counts, bigram_counts))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CryptoError> {
+        Ok(std::fs::write(path, self.to_bytes())?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<LanguageModel, CryptoError> {
+        LanguageModel::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+impl std::fmt::Debug for LanguageModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total = self
+            .unigram_counts
+            .iter()
+            .fold(0u64, |total, c| total.saturating_add(*c));
+        write!(f, "LanguageModel(<trained on {} bytes>)", total)
+    }
+}
+
+/// Scores with the bigram model, so neighbouring bytes must be neighbours in the plaintext.
+impl Scorer for LanguageModel {
+    fn score(&self, candidate: &[u8]) -> f64 {
+        self.log_probability(candidate).unwrap_or(0.0)
+    }
+}
+
+/// Scores with the unigram model only, e.g. for the transposed columns of a repeating key XOR
+/// ciphertext.
+#[derive(Debug, Clone, Copy)]
+pub struct Unigrams<'a>(pub &'a LanguageModel);
+
+impl Scorer for Unigrams<'_> {
+    fn score(&self, candidate: &[u8]) -> f64 {
+        self.0.unigram_log_probability(candidate).unwrap_or(0.0)
+    }
+}
+
+fn bigram_index(first: u8, second: u8) -> usize {
+    (first as usize) << 8 | second as usize
+}
+
+fn write_sparse(out: &mut Vec<u8>, counts: &[u64], write_index: impl Fn(usize, &mut Vec<u8>)) {
+    let present: Vec<_> = counts
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| **c != 0)
+        .collect();
+    write_varint(out, present.len() as u64);
+    for (index, count) in present {
+        write_index(index, out);
+        write_varint(out, *count);
+    }
+}
+
+/// LEB128, seven bits at a time with the high bit set on all but the last byte. Counts up to
+/// `u32::MAX` are written the same way as they were when the counts were `u32`, so older model
+/// files still load.
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], CryptoError> {
+        if self.0.len() < len {
+            return Err(CryptoError::InvalidModel("truncated"));
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<u64, CryptoError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.bytes(1)?[0];
+            let bits = (byte & 0x7f) as u64;
+            if bits << shift >> shift != bits {
+                return Err(CryptoError::InvalidModel("count too large"));
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(CryptoError::InvalidModel("count too large"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_english_prefers_english() {
+        let model = LanguageModel::english();
+        let english = b"Nothing ventured, nothing gained";
+        let gibberish: Vec<u8> = english.iter().map(|b| b ^ 0x15).collect();
+        assert!(model.score(english) > model.score(&gibberish));
+        assert!(Unigrams(model).score(english) > Unigrams(model).score(&gibberish));
+    }
+
+    #[test]
+    fn test_train_other_language() {
+        let danish = "Der var engang en soldat, som kom marcherende hen ad landevejen, én, to! \
+            én, to! han havde sit tornyster på ryggen og en sabel ved siden, for han havde været \
+            i krigen, og nu skulle han hjem. Så mødte han en gammel heks på landevejen.";
+        let model = LanguageModel::train(danish.as_bytes()).unwrap();
+        let sample = "han mødte en gammel soldat på vejen".as_bytes();
+        let english = LanguageModel::english();
+        assert!(model.log_probability(sample).unwrap() > english.log_probability(sample).unwrap());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let model = LanguageModel::english();
+        let bytes = model.to_bytes();
+        assert!(bytes.len() < 8 * 1024);
+        assert_eq!(*model, LanguageModel::from_bytes(&bytes).unwrap());
+
+        let path = std::env::temp_dir().join(format!("cryptopals-{}.model", std::process::id()));
+        model.save(&path).unwrap();
+        let loaded = LanguageModel::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(*model, loaded.unwrap());
+    }
+
+    #[test]
+    fn test_from_bytes_errors() {
+        let bytes = LanguageModel::english().to_bytes();
+        assert_eq!(
+            Err(CryptoError::InvalidModel("truncated")),
+            LanguageModel::from_bytes(&bytes[..bytes.len() - 1])
+        );
+        assert_eq!(
+            Err(CryptoError::InvalidModel("unknown file format")),
+            LanguageModel::from_bytes(b"PK\x03\x04")
+        );
+        assert_eq!(Err(CryptoError::EmptyInput), LanguageModel::train(b""));
+        let mut too_large = MAGIC.to_vec();
+        too_large.extend_from_slice(&[1, b'a', 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        too_large.extend_from_slice(&[0xff, 0xff, 0x02, 0]);
+        assert_eq!(
+            Err(CryptoError::InvalidModel("count too large")),
+            LanguageModel::from_bytes(&too_large)
+        );
+    }
+
+    #[test]
+    fn test_counts_above_u32() {
+        let mut unigram_counts = vec![0; 256];
+        unigram_counts[b'a' as usize] = u32::MAX as u64 + 1;
+        unigram_counts[b'b' as usize] = u64::MAX;
+        let model = LanguageModel::from_counts(unigram_counts, vec![0; 256 * 256]);
+        assert_eq!(model, LanguageModel::from_bytes(&model.to_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_short_texts() {
+        let text = b"Sometimes the questions are complicated and the answers are simple. \
+            Nobody wrote this sentence into the bundled corpus, so it is a fair sample.";
+        let model = LanguageModel::english();
+        let samples: Vec<&[u8]> = text.windows(10).step_by(3).collect();
+        let recovered = samples
+            .iter()
+            .filter(|sample| {
+                let best = (0..=255u8)
+                    .map(|key| sample.iter().map(|b| b ^ key).collect::<Vec<_>>())
+                    .max_by(|a, b| model.score(a).total_cmp(&model.score(b)))
+                    .unwrap();
+                best == **sample
+            })
+            .count();
+        assert!(recovered * 100 >= samples.len() * 95);
+    }
+}
@@ -1,4 +1,17 @@
+pub mod bits;
+pub mod challenges;
+pub mod cli;
+pub mod crib_drag;
 pub mod data;
+pub mod error;
+pub mod file_formats;
+pub mod homebrew_aes;
+pub mod key_length;
+pub mod language_model;
+pub mod modes;
 pub mod plot;
+pub mod recipe;
+pub mod scoring;
 pub mod set1;
-pub mod set2;
\ No newline at end of file
+pub mod set2;
+pub mod xor_variants;
\ No newline at end of file
@@ -0,0 +1,742 @@
+use std::io::{Read, Write};
+
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockDecrypt, BlockEncrypt, NewBlockCipher};
+use aes::{Aes128, Aes192, Aes256};
+
+use crate::data::{Ciphertext, Iv, Key, Plaintext};
+use crate::error::CryptoError;
+use crate::set2::challenge9::{pkcs7_pad, pkcs7_unpad, Padding};
+
+pub trait BlockCipher: Sized {
+    const BLOCK_SIZE: usize;
+    const KEY_SIZE: usize;
+
+    fn with_key(key: &[u8]) -> Result<Self, CryptoError>;
+
+    /// `block` must be exactly `BLOCK_SIZE` bytes long.
+    fn encrypt_block(&self, block: &mut [u8]);
+
+    /// `block` must be exactly `BLOCK_SIZE` bytes long.
+    fn decrypt_block(&self, block: &mut [u8]);
+}
+
+macro_rules! impl_block_cipher_for_aes {
+    ($cipher:ty, $key_size:expr) => {
+        impl BlockCipher for $cipher {
+            const BLOCK_SIZE: usize = 16;
+            const KEY_SIZE: usize = $key_size;
+
+            fn with_key(key: &[u8]) -> Result<Self, CryptoError> {
+                <$cipher as NewBlockCipher>::new_from_slice(key).map_err(|_| {
+                    CryptoError::InvalidKeyLength {
+                        expected: &[$key_size],
+                        actual: key.len(),
+                    }
+                })
+            }
+
+            fn encrypt_block(&self, block: &mut [u8]) {
+                BlockEncrypt::encrypt_block(self, GenericArray::from_mut_slice(block));
+            }
+
+            fn decrypt_block(&self, block: &mut [u8]) {
+                BlockDecrypt::decrypt_block(self, GenericArray::from_mut_slice(block));
+            }
+        }
+    };
+}
+
+impl_block_cipher_for_aes!(Aes128, 16);
+impl_block_cipher_for_aes!(Aes192, 24);
+impl_block_cipher_for_aes!(Aes256, 32);
+
+pub fn ecb_encrypt<C: BlockCipher>(
+    plaintext: &Plaintext,
+    key: &Key,
+    padding: Padding,
+) -> Result<Ciphertext, CryptoError> {
+    let cipher = C::with_key(key.as_bytes())?;
+    let mut data = pad::<C>(plaintext, padding)?;
+    ecb_encrypt_blocks(&cipher, &mut data)?;
+    Ok(Ciphertext(data))
+}
+
+pub fn ecb_decrypt<C: BlockCipher>(
+    ciphertext: &Ciphertext,
+    key: &Key,
+    padding: Padding,
+) -> Result<Plaintext, CryptoError> {
+    let cipher = C::with_key(key.as_bytes())?;
+    let mut data = ciphertext.0.clone();
+    ecb_decrypt_blocks(&cipher, &mut data)?;
+    unpad::<C>(Plaintext(data), padding)
+}
+
+pub fn cbc_encrypt<C: BlockCipher>(
+    plaintext: &Plaintext,
+    key: &Key,
+    iv: &Iv,
+    padding: Padding,
+) -> Result<Ciphertext, CryptoError> {
+    let cipher = C::with_key(key.as_bytes())?;
+    let mut chain = iv.as_bytes().to_vec();
+    let mut data = pad::<C>(plaintext, padding)?;
+    cbc_encrypt_blocks(&cipher, &mut chain, &mut data)?;
+    Ok(Ciphertext(data))
+}
+
+pub fn cbc_decrypt<C: BlockCipher>(
+    ciphertext: &Ciphertext,
+    key: &Key,
+    iv: &Iv,
+    padding: Padding,
+) -> Result<Plaintext, CryptoError> {
+    let cipher = C::with_key(key.as_bytes())?;
+    let mut chain = iv.as_bytes().to_vec();
+    let mut data = ciphertext.0.clone();
+    cbc_decrypt_blocks(&cipher, &mut chain, &mut data)?;
+    unpad::<C>(Plaintext(data), padding)
+}
+
+/// Encrypts `data` in place. Fails without touching `data` if it isn't block aligned.
+pub fn ecb_encrypt_blocks<C: BlockCipher>(cipher: &C, data: &mut [u8]) -> Result<(), CryptoError> {
+    check_block_aligned::<C>(data)?;
+    data.chunks_exact_mut(C::BLOCK_SIZE)
+        .for_each(|block| cipher.encrypt_block(block));
+    Ok(())
+}
+
+pub fn ecb_decrypt_blocks<C: BlockCipher>(cipher: &C, data: &mut [u8]) -> Result<(), CryptoError> {
+    check_block_aligned::<C>(data)?;
+    data.chunks_exact_mut(C::BLOCK_SIZE)
+        .for_each(|block| cipher.decrypt_block(block));
+    Ok(())
+}
+
+/// Encrypts `data` in place. `chain` starts out as the IV and is left holding the last
+/// ciphertext block, so a long message can be processed in several calls.
+pub fn cbc_encrypt_blocks<C: BlockCipher>(
+    cipher: &C,
+    chain: &mut [u8],
+    data: &mut [u8],
+) -> Result<(), CryptoError> {
+    check_iv::<C>(chain)?;
+    check_block_aligned::<C>(data)?;
+    for block in data.chunks_exact_mut(C::BLOCK_SIZE) {
+        xor_in_place(block, chain);
+        cipher.encrypt_block(block);
+        chain.copy_from_slice(block);
+    }
+    Ok(())
+}
+
+pub fn cbc_decrypt_blocks<C: BlockCipher>(
+    cipher: &C,
+    chain: &mut [u8],
+    data: &mut [u8],
+) -> Result<(), CryptoError> {
+    check_iv::<C>(chain)?;
+    check_block_aligned::<C>(data)?;
+    let mut next_chain = vec![0; C::BLOCK_SIZE];
+    for block in data.chunks_exact_mut(C::BLOCK_SIZE) {
+        next_chain.copy_from_slice(block);
+        cipher.decrypt_block(block);
+        xor_in_place(block, chain);
+        chain.copy_from_slice(&next_chain);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BlockMode<'a> {
+    Ecb,
+    Cbc(&'a Iv),
+}
+
+/// How much is read, encrypted and written at a time by `encrypt_stream` and `decrypt_stream`.
+const STREAM_CHUNK_BLOCKS: usize = 4096;
+
+/// Encrypts everything `reader` yields into `writer` a chunk at a time, so large files never
+/// have to fit in memory. Returns the number of bytes written.
+pub fn encrypt_stream<C: BlockCipher>(
+    mut reader: impl Read,
+    mut writer: impl Write,
+    key: &Key,
+    mode: BlockMode,
+    padding: Padding,
+) -> Result<u64, CryptoError> {
+    let mut stream = BlockStream::<C>::new(key, mode)?;
+    let mut chunk = vec![0; STREAM_CHUNK_BLOCKS * C::BLOCK_SIZE];
+    let mut written = 0;
+    loop {
+        let len = read_chunk(&mut reader, &mut chunk)?;
+        if len < chunk.len() {
+            if padding == Padding::None {
+                stream.check_total_aligned(written + len as u64)?;
+            }
+            let mut last = pad::<C>(&Plaintext(chunk[..len].to_vec()), padding)?;
+            stream.encrypt(&mut last)?;
+            writer.write_all(&last)?;
+            writer.flush()?;
+            return Ok(written + last.len() as u64);
+        }
+        stream.encrypt(&mut chunk)?;
+        writer.write_all(&chunk)?;
+        written += len as u64;
+    }
+}
+
+/// Decrypts everything `reader` yields into `writer` a chunk at a time. The last block is held
+/// back until the end of the input, so the padding can be checked and removed. When that
+/// fails, `writer` has already been given everything before the last block.
+pub fn decrypt_stream<C: BlockCipher>(
+    mut reader: impl Read,
+    mut writer: impl Write,
+    key: &Key,
+    mode: BlockMode,
+    padding: Padding,
+) -> Result<u64, CryptoError> {
+    let mut stream = BlockStream::<C>::new(key, mode)?;
+    let mut chunk = vec![0; STREAM_CHUNK_BLOCKS * C::BLOCK_SIZE];
+    let mut held_back = vec![];
+    let mut read = 0;
+    let mut written = 0;
+    loop {
+        let len = read_chunk(&mut reader, &mut chunk)?;
+        read += len as u64;
+        let data = &mut chunk[..len];
+        if len < STREAM_CHUNK_BLOCKS * C::BLOCK_SIZE {
+            stream.check_total_aligned(read)?;
+            stream.decrypt(data)?;
+            held_back.extend_from_slice(data);
+            let last = unpad::<C>(Plaintext(held_back), padding)?;
+            writer.write_all(&last.0)?;
+            writer.flush()?;
+            return Ok(written + last.len() as u64);
+        }
+        stream.decrypt(data)?;
+        let (ready, last_block) = data.split_at(len - C::BLOCK_SIZE);
+        writer.write_all(&held_back)?;
+        writer.write_all(ready)?;
+        written += (held_back.len() + ready.len()) as u64;
+        held_back = last_block.to_vec();
+    }
+}
+
+/// A cipher and, for CBC, the chain carried from one chunk to the next.
+struct BlockStream<C: BlockCipher> {
+    cipher: C,
+    chain: Option<Vec<u8>>,
+}
+
+impl<C: BlockCipher> BlockStream<C> {
+    fn new(key: &Key, mode: BlockMode) -> Result<Self, CryptoError> {
+        let chain = match mode {
+            BlockMode::Ecb => None,
+            BlockMode::Cbc(iv) => {
+                check_iv::<C>(iv.as_bytes())?;
+                Some(iv.as_bytes().to_vec())
+            }
+        };
+        Ok(BlockStream {
+            cipher: C::with_key(key.as_bytes())?,
+            chain,
+        })
+    }
+
+    fn encrypt(&mut self, data: &mut [u8]) -> Result<(), CryptoError> {
+        match &mut self.chain {
+            None => ecb_encrypt_blocks(&self.cipher, data),
+            Some(chain) => cbc_encrypt_blocks(&self.cipher, chain, data),
+        }
+    }
+
+    fn decrypt(&mut self, data: &mut [u8]) -> Result<(), CryptoError> {
+        match &mut self.chain {
+            None => ecb_decrypt_blocks(&self.cipher, data),
+            Some(chain) => cbc_decrypt_blocks(&self.cipher, chain, data),
+        }
+    }
+
+    /// Reports the length of the whole stream rather than just that of the last chunk.
+    fn check_total_aligned(&self, len: u64) -> Result<(), CryptoError> {
+        if !len.is_multiple_of(C::BLOCK_SIZE as u64) {
+            return Err(CryptoError::NotBlockAligned {
+                len: len as usize,
+                block_size: C::BLOCK_SIZE,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Fills `chunk` unless the end of the input comes first, and returns how much was read.
+fn read_chunk(reader: &mut impl Read, chunk: &mut [u8]) -> Result<usize, CryptoError> {
+    let mut len = 0;
+    while len < chunk.len() {
+        match reader.read(&mut chunk[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(len)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CtrLayout {
+    /// 64 bit nonce followed by a 64 bit little endian block counter, as in Cryptopals.
+    Nonce64Counter64Le,
+    /// 96 bit nonce followed by a 32 bit big endian block counter, as in GCM.
+    Nonce96Counter32Be,
+}
+
+impl CtrLayout {
+    pub fn nonce_len(&self) -> usize {
+        match self {
+            CtrLayout::Nonce64Counter64Le => 8,
+            CtrLayout::Nonce96Counter32Be => 12,
+        }
+    }
+
+    fn max_counter(&self) -> u64 {
+        match self {
+            CtrLayout::Nonce64Counter64Le => u64::MAX,
+            CtrLayout::Nonce96Counter32Be => u32::MAX as u64,
+        }
+    }
+
+    fn counter_block(&self, nonce: &[u8], counter: u64) -> Vec<u8> {
+        let mut block = nonce.to_vec();
+        match self {
+            CtrLayout::Nonce64Counter64Le => block.extend(counter.to_le_bytes()),
+            CtrLayout::Nonce96Counter32Be => block.extend((counter as u32).to_be_bytes()),
+        }
+        block
+    }
+}
+
+/// A CTR keystream positioned at a byte offset. Seeking is free, so the middle of a large
+/// message can be decrypted or patched without generating the keystream for the prefix.
+pub struct Ctr<C: BlockCipher> {
+    cipher: C,
+    layout: CtrLayout,
+    nonce: Vec<u8>,
+    initial_counter: u64,
+    position: u64,
+}
+
+impl<C: BlockCipher> Ctr<C> {
+    pub fn new(key: &Key, nonce: &[u8], layout: CtrLayout) -> Result<Self, CryptoError> {
+        if C::BLOCK_SIZE != 16 {
+            return Err(CryptoError::InvalidBlockSize(C::BLOCK_SIZE));
+        }
+        if nonce.len() != layout.nonce_len() {
+            return Err(CryptoError::InvalidNonceLength {
+                expected: layout.nonce_len(),
+                actual: nonce.len(),
+            });
+        }
+        Ok(Ctr {
+            cipher: C::with_key(key.as_bytes())?,
+            layout,
+            nonce: nonce.to_vec(),
+            initial_counter: 0,
+            position: 0,
+        })
+    }
+
+    /// Sets the counter value used for the first block, e.g. 2 for GCM payloads.
+    pub fn with_initial_counter(mut self, initial_counter: u64) -> Result<Self, CryptoError> {
+        if initial_counter > self.layout.max_counter() {
+            return Err(CryptoError::CounterOverflow);
+        }
+        self.initial_counter = initial_counter;
+        Ok(self)
+    }
+
+    pub fn seek(&mut self, position: u64) {
+        self.position = position;
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// XORs the keystream into `data` and advances the position by `data.len()` bytes.
+    pub fn apply_keystream(&mut self, data: &mut [u8]) -> Result<(), CryptoError> {
+        let block_size = C::BLOCK_SIZE as u64;
+        let end = self
+            .position
+            .checked_add(data.len() as u64)
+            .ok_or(CryptoError::CounterOverflow)?;
+        if end > self.position {
+            let last_counter = self.initial_counter as u128 + ((end - 1) / block_size) as u128;
+            if last_counter > self.layout.max_counter() as u128 {
+                return Err(CryptoError::CounterOverflow);
+            }
+        }
+
+        let mut data = data;
+        while !data.is_empty() {
+            let counter = self.initial_counter + self.position / block_size;
+            let offset_in_block = (self.position % block_size) as usize;
+            let mut keystream = self.layout.counter_block(&self.nonce, counter);
+            self.cipher.encrypt_block(&mut keystream);
+            let len = data.len().min(C::BLOCK_SIZE - offset_in_block);
+            let (now, rest) = data.split_at_mut(len);
+            xor_in_place(now, &keystream[offset_in_block..]);
+            self.position += len as u64;
+            data = rest;
+        }
+        Ok(())
+    }
+}
+
+pub fn ctr_encrypt<C: BlockCipher>(
+    plaintext: &Plaintext,
+    key: &Key,
+    nonce: &[u8],
+    layout: CtrLayout,
+) -> Result<Ciphertext, CryptoError> {
+    let mut data = plaintext.0.clone();
+    Ctr::<C>::new(key, nonce, layout)?.apply_keystream(&mut data)?;
+    Ok(Ciphertext(data))
+}
+
+pub fn ctr_decrypt<C: BlockCipher>(
+    ciphertext: &Ciphertext,
+    key: &Key,
+    nonce: &[u8],
+    layout: CtrLayout,
+) -> Result<Plaintext, CryptoError> {
+    let mut data = ciphertext.0.clone();
+    Ctr::<C>::new(key, nonce, layout)?.apply_keystream(&mut data)?;
+    Ok(Plaintext(data))
+}
+
+fn pad<C: BlockCipher>(plaintext: &Plaintext, padding: Padding) -> Result<Vec<u8>, CryptoError> {
+    match padding {
+        Padding::Pkcs7 => Ok(pkcs7_pad(plaintext, C::BLOCK_SIZE)?.0),
+        Padding::None => Ok(plaintext.0.clone()),
+    }
+}
+
+fn unpad<C: BlockCipher>(plaintext: Plaintext, padding: Padding) -> Result<Plaintext, CryptoError> {
+    match padding {
+        Padding::Pkcs7 => Ok(pkcs7_unpad(&plaintext, C::BLOCK_SIZE)?),
+        Padding::None => Ok(plaintext),
+    }
+}
+
+fn check_block_aligned<C: BlockCipher>(data: &[u8]) -> Result<(), CryptoError> {
+    if !data.len().is_multiple_of(C::BLOCK_SIZE) {
+        return Err(CryptoError::NotBlockAligned {
+            len: data.len(),
+            block_size: C::BLOCK_SIZE,
+        });
+    }
+    Ok(())
+}
+
+fn check_iv<C: BlockCipher>(iv: &[u8]) -> Result<(), CryptoError> {
+    if iv.len() != C::BLOCK_SIZE {
+        return Err(CryptoError::InvalidIvLength {
+            expected: C::BLOCK_SIZE,
+            actual: iv.len(),
+        });
+    }
+    Ok(())
+}
+
+fn xor_in_place(data: &mut [u8], other: &[u8]) {
+    data.iter_mut().zip(other).for_each(|(d, o)| *d ^= o);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::AesKey;
+    use std::str::FromStr;
+
+    // Test vectors from NIST SP 800-38A, appendix F.
+    const SP800_38A_PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+    const SP800_38A_IV: &str = "000102030405060708090a0b0c0d0e0f";
+
+    fn vector(hex_string: &str) -> Vec<u8> {
+        hex::decode(hex_string).unwrap()
+    }
+
+    #[test]
+    fn test_ecb_aes128_sp800_38a() {
+        let key = AesKey::try_from(vector("2b7e151628aed2a6abf7158809cf4f3c")).unwrap();
+        let plaintext = Plaintext(vector(SP800_38A_PLAINTEXT));
+        let ciphertext = ecb_encrypt::<Aes128>(&plaintext, &key, Padding::None).unwrap();
+        assert_eq!(
+            "3ad77bb40d7a3660a89ecaf32466ef97f5d3d58503b9699de785895a96fdbaaf43b1cd7f598ece23881b00e3ed0306887b0c785e27e8ad3f8223207104725dd4",
+            hex::encode(&ciphertext.0)
+        );
+        let decrypted = ecb_decrypt::<Aes128>(&ciphertext, &key, Padding::None).unwrap();
+        assert_eq!(plaintext.0, decrypted.0);
+    }
+
+    #[test]
+    fn test_cbc_aes128_sp800_38a() {
+        let key = AesKey::try_from(vector("2b7e151628aed2a6abf7158809cf4f3c")).unwrap();
+        let iv = Iv::try_from(vector(SP800_38A_IV)).unwrap();
+        let plaintext = Plaintext(vector(SP800_38A_PLAINTEXT));
+        let ciphertext = cbc_encrypt::<Aes128>(&plaintext, &key, &iv, Padding::None).unwrap();
+        assert_eq!(
+            "7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b273bed6b8e3c1743b7116e69e222295163ff1caa1681fac09120eca307586e1a7",
+            hex::encode(&ciphertext.0)
+        );
+        let decrypted = cbc_decrypt::<Aes128>(&ciphertext, &key, &iv, Padding::None).unwrap();
+        assert_eq!(plaintext.0, decrypted.0);
+    }
+
+    #[test]
+    fn test_cbc_aes192_sp800_38a() {
+        let key =
+            AesKey::try_from(vector("8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b")).unwrap();
+        let iv = Iv::try_from(vector(SP800_38A_IV)).unwrap();
+        let plaintext = Plaintext(vector(SP800_38A_PLAINTEXT));
+        let ciphertext = cbc_encrypt::<Aes192>(&plaintext, &key, &iv, Padding::None).unwrap();
+        assert_eq!(
+            "4f021db243bc633d7178183a9fa071e8b4d9ada9ad7dedf4e5e738763f69145a571b242012fb7ae07fa9baac3df102e008b0e27988598881d920a9e64f5615cd",
+            hex::encode(&ciphertext.0)
+        );
+    }
+
+    #[test]
+    fn test_cbc_aes256_sp800_38a() {
+        let key = AesKey::try_from(vector(
+            "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+        ))
+        .unwrap();
+        let iv = Iv::try_from(vector(SP800_38A_IV)).unwrap();
+        let plaintext = Plaintext(vector(SP800_38A_PLAINTEXT));
+        let ciphertext = cbc_encrypt::<Aes256>(&plaintext, &key, &iv, Padding::None).unwrap();
+        assert_eq!(
+            "f58c4c04d6e5f1ba779eabfb5f7bfbd69cfc4e967edb808d679f777bc6702c7d39f23369a9d9bacfa530e26304231461b2eb05e2c39be9fcda6c19078c6a9d1b",
+            hex::encode(&ciphertext.0)
+        );
+    }
+
+    #[test]
+    fn test_partial_block_is_an_error() {
+        let key = AesKey::from_str("YELLOW SUBMARINE").unwrap();
+        let iv = Iv::block(&[0; 16]).unwrap();
+        let ciphertext = Ciphertext(vec![0; 20]);
+        assert_eq!(
+            CryptoError::NotBlockAligned {
+                len: 20,
+                block_size: 16
+            },
+            ecb_decrypt::<Aes128>(&ciphertext, &key, Padding::None).unwrap_err()
+        );
+        assert!(cbc_decrypt::<Aes128>(&ciphertext, &key, &iv, Padding::None).is_err());
+        let plaintext = Plaintext(vec![0; 20]);
+        assert!(ecb_encrypt::<Aes128>(&plaintext, &key, Padding::None).is_err());
+        assert!(cbc_encrypt::<Aes128>(&plaintext, &key, &iv, Padding::None).is_err());
+    }
+
+    #[test]
+    fn test_key_length_must_match_cipher() {
+        let plaintext = Plaintext(vec![0; 16]);
+        let key = AesKey::aes192(&[0; 24]).unwrap();
+        assert_eq!(
+            Err(CryptoError::InvalidKeyLength {
+                expected: &[16],
+                actual: 24
+            }),
+            ecb_encrypt::<Aes128>(&plaintext, &key, Padding::None)
+        );
+    }
+
+    #[test]
+    fn test_wrong_key_and_iv_length_is_an_error() {
+        let plaintext = Plaintext(vec![0; 16]);
+        let short_key = Key(vec![0; 15]);
+        assert!(ecb_encrypt::<Aes128>(&plaintext, &short_key, Padding::None).is_err());
+        let key = Key(vec![0; 16]);
+        let short_iv = Iv::try_from(vec![0; 8]).unwrap();
+        assert_eq!(
+            Err(CryptoError::InvalidIvLength {
+                expected: 16,
+                actual: 8
+            }),
+            cbc_encrypt::<Aes128>(&plaintext, &key, &short_iv, Padding::None)
+        );
+    }
+
+    #[test]
+    fn test_cbc_in_several_calls() {
+        let cipher = Aes256::with_key(&[7; 32]).unwrap();
+        let mut whole = vec![42; 64];
+        let mut chain = vec![1; 16];
+        cbc_encrypt_blocks(&cipher, &mut chain, &mut whole).unwrap();
+
+        let mut parts = vec![42; 64];
+        let mut chain = vec![1; 16];
+        let (first, second) = parts.split_at_mut(32);
+        cbc_encrypt_blocks(&cipher, &mut chain, first).unwrap();
+        cbc_encrypt_blocks(&cipher, &mut chain, second).unwrap();
+        assert_eq!(whole, parts);
+    }
+
+    #[test]
+    fn test_stream_matches_whole_message() {
+        let key = AesKey::try_from(vec![7; 16]).unwrap();
+        let iv = Iv::block(&[1; 16]).unwrap();
+        let chunk_len = STREAM_CHUNK_BLOCKS * 16;
+        for len in [
+            0,
+            5,
+            16,
+            chunk_len - 1,
+            chunk_len,
+            chunk_len + 16,
+            2 * chunk_len + 3,
+        ] {
+            let plaintext = Plaintext((0..len).map(|i| i as u8).collect());
+            let expected = cbc_encrypt::<Aes128>(&plaintext, &key, &iv, Padding::Pkcs7).unwrap();
+            let mut ciphertext = vec![];
+            let mode = BlockMode::Cbc(&iv);
+            encrypt_stream::<Aes128>(
+                &plaintext.0[..],
+                &mut ciphertext,
+                &key,
+                mode,
+                Padding::Pkcs7,
+            )
+            .unwrap();
+            assert_eq!(expected.0, ciphertext);
+
+            let mut decrypted = vec![];
+            let written = decrypt_stream::<Aes128>(
+                &ciphertext[..],
+                &mut decrypted,
+                &key,
+                mode,
+                Padding::Pkcs7,
+            )
+            .unwrap();
+            assert_eq!(plaintext.0, decrypted);
+            assert_eq!(len as u64, written);
+        }
+    }
+
+    #[test]
+    fn test_stream_errors() {
+        let key = AesKey::try_from(vec![7; 16]).unwrap();
+        let mut output = vec![];
+        let unaligned = vec![0; STREAM_CHUNK_BLOCKS * 16 + 5];
+        assert_eq!(
+            Err(CryptoError::NotBlockAligned {
+                len: unaligned.len(),
+                block_size: 16
+            }),
+            encrypt_stream::<Aes128>(
+                &unaligned[..],
+                &mut output,
+                &key,
+                BlockMode::Ecb,
+                Padding::None
+            )
+        );
+        let unpadded = ecb_encrypt::<Aes128>(&Plaintext(vec![0; 32]), &key, Padding::None).unwrap();
+        assert!(matches!(
+            decrypt_stream::<Aes128>(
+                &unpadded.0[..],
+                &mut output,
+                &key,
+                BlockMode::Ecb,
+                Padding::Pkcs7
+            ),
+            Err(CryptoError::Padding(_))
+        ));
+        let short_iv = Iv::try_from(vec![0; 8]).unwrap();
+        assert!(matches!(
+            decrypt_stream::<Aes128>(
+                &unpadded.0[..],
+                &mut output,
+                &key,
+                BlockMode::Cbc(&short_iv),
+                Padding::None
+            ),
+            Err(CryptoError::InvalidIvLength { .. })
+        ));
+    }
+
+    #[test]
+    fn test_ctr_cryptopals_layout() {
+        let ciphertext = Ciphertext(
+            base64::decode(
+                "L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==",
+            )
+            .unwrap(),
+        );
+        let key = AesKey::from_str("YELLOW SUBMARINE").unwrap();
+        let plaintext =
+            ctr_decrypt::<Aes128>(&ciphertext, &key, &[0; 8], CtrLayout::Nonce64Counter64Le)
+                .unwrap();
+        assert_eq!(
+            "Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby ",
+            plaintext.to_string()
+        );
+    }
+
+    #[test]
+    fn test_ctr_gcm_layout_sp800_38a() {
+        let key = AesKey::try_from(vector("2b7e151628aed2a6abf7158809cf4f3c")).unwrap();
+        let nonce = vector("f0f1f2f3f4f5f6f7f8f9fafb");
+        let plaintext = Plaintext(vector(SP800_38A_PLAINTEXT));
+        let mut data = plaintext.0.clone();
+        Ctr::<Aes128>::new(&key, &nonce, CtrLayout::Nonce96Counter32Be)
+            .unwrap()
+            .with_initial_counter(0xfcfdfeff)
+            .unwrap()
+            .apply_keystream(&mut data)
+            .unwrap();
+        assert_eq!(
+            "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee",
+            hex::encode(&data)
+        );
+    }
+
+    #[test]
+    fn test_ctr_seek() {
+        let key = AesKey::from_str("YELLOW SUBMARINE").unwrap();
+        let nonce = [3; 8];
+        let plaintext = Plaintext((0..200).collect());
+        let ciphertext =
+            ctr_encrypt::<Aes128>(&plaintext, &key, &nonce, CtrLayout::Nonce64Counter64Le).unwrap();
+
+        let mut ctr = Ctr::<Aes128>::new(&key, &nonce, CtrLayout::Nonce64Counter64Le).unwrap();
+        ctr.seek(37);
+        let mut middle = ciphertext.0[37..101].to_vec();
+        ctr.apply_keystream(&mut middle).unwrap();
+        assert_eq!(plaintext.0[37..101], middle[..]);
+        assert_eq!(101, ctr.position());
+    }
+
+    #[test]
+    fn test_ctr_gcm_layout_counter_wraparound_is_an_error() {
+        let key = AesKey::from_str("YELLOW SUBMARINE").unwrap();
+        let mut ctr = Ctr::<Aes128>::new(&key, &[0; 12], CtrLayout::Nonce96Counter32Be)
+            .unwrap()
+            .with_initial_counter(u32::MAX as u64)
+            .unwrap();
+        let mut data = vec![0; 16];
+        assert!(ctr.apply_keystream(&mut data).is_ok());
+        assert!(ctr.apply_keystream(&mut data).is_err());
+    }
+
+    #[test]
+    fn test_ctr_wrong_nonce_length_is_an_error() {
+        let key = AesKey::from_str("YELLOW SUBMARINE").unwrap();
+        assert!(Ctr::<Aes128>::new(&key, &[0; 8], CtrLayout::Nonce96Counter32Be).is_err());
+    }
+}
@@ -0,0 +1,484 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use aes::{Aes128, Aes192, Aes256};
+
+use crate::data::{AesKey, Ciphertext, Encoding, Iv, Key, Plaintext};
+use crate::error::CryptoError;
+use crate::modes::{cbc_decrypt, cbc_encrypt, ecb_decrypt, ecb_encrypt, BlockCipher};
+use crate::set1::challenge2::fixed_xor;
+use crate::set1::challenge5::repeating_key_xor_encrypt;
+use crate::set2::challenge9::{pkcs7_pad, pkcs7_unpad, Padding};
+
+/// A pipeline of operations, each working on the output of the one before it.
+///
+/// In the text format each operation is a name followed by `parameter=value` pairs, one
+/// operation per line or separated by `|`. Byte values are text, or `hex:` or `base64:`
+/// followed by the bytes in that encoding. Values containing spaces are quoted, and `#` starts
+/// a comment:
+///
+/// ```text
+/// # Challenge 10
+/// from-base64
+/// aes-cbc-decrypt key="YELLOW SUBMARINE" iv=hex:00000000000000000000000000000000
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recipe {
+    pub operations: Vec<Operation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    Decode(Encoding),
+    Encode(Encoding),
+    /// XORs with bytes of the same length as the data.
+    FixedXor(Vec<u8>),
+    RepeatingKeyXor(Key),
+    AesEncrypt(AesParameters),
+    AesDecrypt(AesParameters),
+    Pkcs7Pad(usize),
+    Pkcs7Unpad(usize),
+}
+
+/// AES-128, AES-192 or AES-256 depending on the key length, in CBC mode if there is an IV and
+/// in ECB mode otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AesParameters {
+    pub key: AesKey,
+    pub iv: Option<Iv>,
+    pub padding: Padding,
+}
+
+impl Recipe {
+    pub fn run(&self, data: Vec<u8>) -> Result<Vec<u8>, CryptoError> {
+        self.operations
+            .iter()
+            .enumerate()
+            .try_fold(data, |data, (index, operation)| {
+                operation
+                    .apply(data)
+                    .map_err(|error| CryptoError::RecipeStep {
+                        step: index + 1,
+                        operation: operation.name(),
+                        error: Box::new(error),
+                    })
+            })
+    }
+
+    /// The recipe in the text format with all bytes in hex, keys and IVs included, so it can be
+    /// parsed back. Use `to_string` for a version that is safe to log.
+    pub fn to_recipe_string(&self) -> String {
+        self.write(true)
+    }
+
+    fn write(&self, reveal: bool) -> String {
+        let mut text = String::new();
+        for operation in &self.operations {
+            text.push_str(operation.name());
+            for (name, value) in operation.parameters(reveal) {
+                text.push_str(&format!(" {}={}", name, value));
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+impl Operation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Decode(Encoding::Hex) => "from-hex",
+            Operation::Decode(Encoding::Base64) => "from-base64",
+            Operation::Decode(Encoding::Raw) => "from-raw",
+            Operation::Encode(Encoding::Hex) => "to-hex",
+            Operation::Encode(Encoding::Base64) => "to-base64",
+            Operation::Encode(Encoding::Raw) => "to-raw",
+            Operation::FixedXor(_) => "fixed-xor",
+            Operation::RepeatingKeyXor(_) => "repeating-key-xor",
+            Operation::AesEncrypt(AesParameters { iv: None, .. }) => "aes-ecb-encrypt",
+            Operation::AesEncrypt(_) => "aes-cbc-encrypt",
+            Operation::AesDecrypt(AesParameters { iv: None, .. }) => "aes-ecb-decrypt",
+            Operation::AesDecrypt(_) => "aes-cbc-decrypt",
+            Operation::Pkcs7Pad(_) => "pkcs7-pad",
+            Operation::Pkcs7Unpad(_) => "pkcs7-unpad",
+        }
+    }
+
+    pub fn apply(&self, data: Vec<u8>) -> Result<Vec<u8>, CryptoError> {
+        match self {
+            Operation::Decode(encoding) => encoding.decode(&data),
+            Operation::Encode(encoding) => Ok(encoding.encode(&data)),
+            Operation::FixedXor(bytes) => fixed_xor(&data, bytes),
+            Operation::RepeatingKeyXor(key) => {
+                Ok(repeating_key_xor_encrypt(&Plaintext(data), key)?.0)
+            }
+            Operation::AesEncrypt(parameters) => match parameters.key.len() {
+                16 => aes_encrypt::<Aes128>(parameters, data),
+                24 => aes_encrypt::<Aes192>(parameters, data),
+                _ => aes_encrypt::<Aes256>(parameters, data),
+            },
+            Operation::AesDecrypt(parameters) => match parameters.key.len() {
+                16 => aes_decrypt::<Aes128>(parameters, data),
+                24 => aes_decrypt::<Aes192>(parameters, data),
+                _ => aes_decrypt::<Aes256>(parameters, data),
+            },
+            Operation::Pkcs7Pad(block_size) => Ok(pkcs7_pad(&Plaintext(data), *block_size)?.0),
+            Operation::Pkcs7Unpad(block_size) => Ok(pkcs7_unpad(&Plaintext(data), *block_size)?.0),
+        }
+    }
+
+    fn parse(name: &str, parameters: &mut Parameters) -> Result<Operation, String> {
+        let operation = match name {
+            "from-hex" => Operation::Decode(Encoding::Hex),
+            "from-base64" => Operation::Decode(Encoding::Base64),
+            "from-raw" => Operation::Decode(Encoding::Raw),
+            "to-hex" => Operation::Encode(Encoding::Hex),
+            "to-base64" => Operation::Encode(Encoding::Base64),
+            "to-raw" => Operation::Encode(Encoding::Raw),
+            "fixed-xor" => Operation::FixedXor(parameters.bytes("with")?),
+            "repeating-key-xor" => {
+                let key = parameters.bytes("key")?;
+                if key.is_empty() {
+                    return Err("the key is empty".to_string());
+                }
+                Operation::RepeatingKeyXor(Key(key))
+            }
+            "aes-ecb-encrypt" => Operation::AesEncrypt(parameters.aes(false)?),
+            "aes-ecb-decrypt" => Operation::AesDecrypt(parameters.aes(false)?),
+            "aes-cbc-encrypt" => Operation::AesEncrypt(parameters.aes(true)?),
+            "aes-cbc-decrypt" => Operation::AesDecrypt(parameters.aes(true)?),
+            "pkcs7-pad" => Operation::Pkcs7Pad(parameters.block_size()?),
+            "pkcs7-unpad" => Operation::Pkcs7Unpad(parameters.block_size()?),
+            _ => return Err(format!("unknown operation {}", name)),
+        };
+        Ok(operation)
+    }
+
+    /// The parameters in the text format. Keys and IVs are left out unless `reveal` is set.
+    fn parameters(&self, reveal: bool) -> Vec<(&'static str, String)> {
+        let secret = |bytes: &[u8]| {
+            if reveal {
+                hex_value(bytes)
+            } else {
+                format!("<redacted {} bytes>", bytes.len())
+            }
+        };
+        match self {
+            Operation::Decode(_) | Operation::Encode(_) => vec![],
+            Operation::FixedXor(bytes) => vec![("with", hex_value(bytes))],
+            Operation::RepeatingKeyXor(key) => vec![("key", secret(&key.0))],
+            Operation::AesEncrypt(parameters) | Operation::AesDecrypt(parameters) => {
+                let mut values = vec![("key", secret(parameters.key.as_bytes()))];
+                if let Some(iv) = &parameters.iv {
+                    values.push(("iv", secret(iv.as_bytes())));
+                }
+                if parameters.padding == Padding::None {
+                    values.push(("padding", "none".to_string()));
+                }
+                values
+            }
+            Operation::Pkcs7Pad(block_size) | Operation::Pkcs7Unpad(block_size) => {
+                vec![("block-size", block_size.to_string())]
+            }
+        }
+    }
+}
+
+fn aes_encrypt<C: BlockCipher>(
+    parameters: &AesParameters,
+    data: Vec<u8>,
+) -> Result<Vec<u8>, CryptoError> {
+    let plaintext = Plaintext(data);
+    let ciphertext = match &parameters.iv {
+        None => ecb_encrypt::<C>(&plaintext, &parameters.key, parameters.padding)?,
+        Some(iv) => cbc_encrypt::<C>(&plaintext, &parameters.key, iv, parameters.padding)?,
+    };
+    Ok(ciphertext.0)
+}
+
+fn aes_decrypt<C: BlockCipher>(
+    parameters: &AesParameters,
+    data: Vec<u8>,
+) -> Result<Vec<u8>, CryptoError> {
+    let ciphertext = Ciphertext(data);
+    let plaintext = match &parameters.iv {
+        None => ecb_decrypt::<C>(&ciphertext, &parameters.key, parameters.padding)?,
+        Some(iv) => cbc_decrypt::<C>(&ciphertext, &parameters.key, iv, parameters.padding)?,
+    };
+    Ok(plaintext.0)
+}
+
+fn hex_value(bytes: &[u8]) -> String {
+    format!("hex:{}", hex::encode(bytes))
+}
+
+/// The `name=value` pairs given to an operation, taken out one at a time as they are parsed.
+struct Parameters(HashMap<String, String>);
+
+impl Parameters {
+    fn take(&mut self, name: &str) -> Option<String> {
+        self.0.remove(name)
+    }
+
+    fn bytes(&mut self, name: &str) -> Result<Vec<u8>, String> {
+        let value = self
+            .take(name)
+            .ok_or_else(|| format!("missing parameter {}", name))?;
+        let bytes = if let Some(hex) = value.strip_prefix("hex:") {
+            Encoding::Hex.decode(hex.as_bytes())
+        } else if let Some(base64) = value.strip_prefix("base64:") {
+            Encoding::Base64.decode(base64.as_bytes())
+        } else {
+            Ok(value
+                .strip_prefix("text:")
+                .unwrap_or(&value)
+                .as_bytes()
+                .to_vec())
+        };
+        bytes.map_err(|e| format!("bad value for {}: {}", name, e))
+    }
+
+    fn aes(&mut self, cbc: bool) -> Result<AesParameters, String> {
+        let key = AesKey::new(&self.bytes("key")?).map_err(|e| e.to_string())?;
+        let iv = if cbc {
+            Some(Iv::block(&self.bytes("iv")?).map_err(|e| e.to_string())?)
+        } else {
+            None
+        };
+        let padding = match self.take("padding") {
+            Some(padding) => Padding::from_str(&padding).map_err(|e| e.to_string())?,
+            None => Padding::Pkcs7,
+        };
+        Ok(AesParameters { key, iv, padding })
+    }
+
+    fn block_size(&mut self) -> Result<usize, String> {
+        let block_size = match self.take("block-size") {
+            Some(block_size) => block_size
+                .parse()
+                .map_err(|_| format!("bad block size {}", block_size))?,
+            None => 16,
+        };
+        if !(1..=255).contains(&block_size) {
+            return Err(CryptoError::InvalidBlockSize(block_size).to_string());
+        }
+        Ok(block_size)
+    }
+
+    fn finish(self) -> Result<(), String> {
+        let mut unused: Vec<String> = self.0.into_keys().collect();
+        unused.sort();
+        match unused.as_slice() {
+            [] => Ok(()),
+            _ => Err(format!("unknown parameter {}", unused.join(", "))),
+        }
+    }
+}
+
+/// Splits the text into operations, each with the line it starts on and its words. Quotes
+/// are removed from the words.
+fn tokenize(text: &str) -> Result<Vec<(usize, Vec<String>)>, CryptoError> {
+    let mut operations = vec![];
+    let mut words: Vec<String> = vec![];
+    let mut word: Option<String> = None;
+    let mut line = 1;
+    let mut start_line = 1;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => word.extend(chars.next()),
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            word.push(c)
+                        }
+                        None => {
+                            return Err(CryptoError::InvalidRecipe {
+                                line,
+                                message: "unterminated quote".to_string(),
+                            })
+                        }
+                    }
+                }
+            }
+            '#' if word.is_none() => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                words.extend(word.take());
+                push_operation(&mut operations, &mut words, start_line);
+                line += 1;
+                start_line = line;
+            }
+            '\n' | '|' => {
+                words.extend(word.take());
+                push_operation(&mut operations, &mut words, start_line);
+                if c == '\n' {
+                    line += 1;
+                }
+                start_line = line;
+            }
+            c if c.is_whitespace() => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word.take());
+    push_operation(&mut operations, &mut words, start_line);
+    Ok(operations)
+}
+
+fn push_operation(
+    operations: &mut Vec<(usize, Vec<String>)>,
+    words: &mut Vec<String>,
+    line: usize,
+) {
+    if !words.is_empty() {
+        operations.push((line, std::mem::take(words)));
+    }
+}
+
+impl FromStr for Recipe {
+    type Err = CryptoError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut operations = vec![];
+        for (line, words) in tokenize(text)? {
+            let invalid = |message| CryptoError::InvalidRecipe { line, message };
+            let mut parameters = HashMap::new();
+            for word in &words[1..] {
+                let (name, value) = word
+                    .split_once('=')
+                    .ok_or_else(|| invalid(format!("expected name=value, got {}", word)))?;
+                if parameters
+                    .insert(name.to_string(), value.to_string())
+                    .is_some()
+                {
+                    return Err(invalid(format!("{} is given twice", name)));
+                }
+            }
+            let mut parameters = Parameters(parameters);
+            let operation = Operation::parse(&words[0], &mut parameters).map_err(invalid)?;
+            parameters.finish().map_err(invalid)?;
+            operations.push(operation);
+        }
+        Ok(Recipe { operations })
+    }
+}
+
+/// Writes the recipe in the text format with keys and IVs redacted. It can't be parsed back,
+/// `to_recipe_string` can.
+impl fmt::Display for Recipe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.write(false))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_challenge_10() {
+        let recipe = Recipe::from_str(
+            "# Challenge 10\n\
+             from-base64\n\
+             aes-cbc-decrypt key=\"YELLOW SUBMARINE\" iv=hex:00000000000000000000000000000000\n",
+        )
+        .unwrap();
+        let plaintext = recipe.run(include_bytes!("set2/10.txt").to_vec()).unwrap();
+        assert!(plaintext.starts_with(b"I'm back and I'm ringin' the bell"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let recipe = Recipe::from_str(
+            "repeating-key-xor key=ICE | pkcs7-pad | aes-ecb-encrypt key=base64:WUVMTE9XIFNVQk1BUklORQ== padding=none | to-hex",
+        )
+        .unwrap();
+        assert_eq!(4, recipe.operations.len());
+        assert_eq!(
+            recipe,
+            Recipe::from_str(&recipe.to_recipe_string()).unwrap()
+        );
+
+        let ciphertext = recipe.run(b"Burning 'em".to_vec()).unwrap();
+        let reverse = Recipe::from_str(
+            "from-hex | aes-ecb-decrypt key=\"YELLOW SUBMARINE\" padding=none | pkcs7-unpad \
+             | repeating-key-xor key=hex:494345",
+        )
+        .unwrap();
+        assert_eq!(b"Burning 'em".to_vec(), reverse.run(ciphertext).unwrap());
+    }
+
+    #[test]
+    fn test_display_redacts_secrets() {
+        let recipe = Recipe::from_str(
+            "aes-cbc-decrypt key=\"YELLOW SUBMARINE\" iv=hex:00000000000000000000000000000000 \
+             | repeating-key-xor key=ICE | fixed-xor with=hex:0102",
+        )
+        .unwrap();
+        assert_eq!(
+            "aes-cbc-decrypt key=<redacted 16 bytes> iv=<redacted 16 bytes>\n\
+             repeating-key-xor key=<redacted 3 bytes>\n\
+             fixed-xor with=hex:0102\n",
+            recipe.to_string()
+        );
+        assert!(recipe.to_recipe_string().contains("key=hex:59454c4c4f57"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |text| Recipe::from_str(text).unwrap_err().to_string();
+        assert_eq!(
+            "Invalid recipe on line 2: unknown operation rot13",
+            error("to-hex\nrot13")
+        );
+        assert_eq!(
+            "Invalid recipe on line 1: Invalid key length 3, expected 16, 24 or 32",
+            error("aes-ecb-decrypt key=abc")
+        );
+        assert_eq!(
+            "Invalid recipe on line 1: missing parameter iv",
+            error("aes-cbc-decrypt key=\"YELLOW SUBMARINE\"")
+        );
+        assert_eq!(
+            "Invalid recipe on line 1: unknown parameter size",
+            error("pkcs7-pad size=8")
+        );
+        assert_eq!(
+            "Invalid recipe on line 1: Invalid block size 256",
+            error("pkcs7-pad block-size=256")
+        );
+        assert_eq!(
+            "Invalid recipe on line 1: Invalid block size 0",
+            error("pkcs7-unpad block-size=0")
+        );
+        assert_eq!(
+            "Invalid recipe on line 1: unterminated quote",
+            error("fixed-xor with=\"abc")
+        );
+
+        let recipe = Recipe::from_str("from-hex | pkcs7-unpad").unwrap();
+        assert_eq!(
+            Err(CryptoError::RecipeStep {
+                step: 1,
+                operation: "from-hex",
+                error: Box::new(CryptoError::Hex(hex::FromHexError::OddLength)),
+            }),
+            recipe.run(b"abc".to_vec())
+        );
+        assert!(matches!(
+            recipe.run(b"00".repeat(16)),
+            Err(CryptoError::RecipeStep { step: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,414 @@
+/// Rates how plausible a candidate plaintext is. Higher scores are better. Scores are only
+/// comparable between candidates rated by the same scorer.
+pub trait Scorer: Sync {
+    fn score(&self, candidate: &[u8]) -> f64;
+}
+
+/// Scores English text by combining a chi-squared test of the letter frequencies, the log
+/// probabilities of single bytes, bigrams and trigrams and a penalty for bytes that don't occur
+/// in text. On very short texts the chi-squared statistic is noisy, so the byte probabilities
+/// carry most of the weight by default. The weights can be tuned, e.g. the n-gram weights should
+/// be zero when scoring every n'th byte of a text.
+///
+/// This was the default scorer of the XOR breakers until `LanguageModel::english()` replaced it.
+/// Its scores are on a different scale, so don't compare them with the language model's.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnglishScorer {
+    pub chi_squared_weight: f64,
+    pub unigram_weight: f64,
+    pub bigram_weight: f64,
+    pub trigram_weight: f64,
+    pub unprintable_weight: f64,
+}
+
+impl EnglishScorer {
+    pub const DEFAULT: EnglishScorer = EnglishScorer {
+        chi_squared_weight: 0.05,
+        unigram_weight: 3.0,
+        bigram_weight: 1.0,
+        trigram_weight: 0.5,
+        unprintable_weight: 20.0,
+    };
+
+    /// For text where neighbouring bytes aren't neighbours in the plaintext, such as the
+    /// columns of a transposed repeating key XOR ciphertext.
+    pub const UNIGRAMS_ONLY: EnglishScorer = EnglishScorer {
+        chi_squared_weight: 0.05,
+        unigram_weight: 3.0,
+        bigram_weight: 0.0,
+        trigram_weight: 0.0,
+        unprintable_weight: 20.0,
+    };
+}
+
+impl Default for EnglishScorer {
+    fn default() -> Self {
+        EnglishScorer::DEFAULT
+    }
+}
+
+impl Scorer for EnglishScorer {
+    fn score(&self, candidate: &[u8]) -> f64 {
+        if candidate.is_empty() {
+            return 0.0;
+        }
+        let mut score = -self.chi_squared_weight * chi_squared(candidate) / candidate.len() as f64
+            - self.unprintable_weight * unprintable_fraction(candidate)
+            + self.unigram_weight * (unigram_log_probability(candidate) - UNIGRAM_BASELINE);
+        if self.bigram_weight != 0.0 {
+            if let Some(log_probability) = bigram_log_probability(candidate) {
+                score += self.bigram_weight * (log_probability - BIGRAM_BASELINE);
+            }
+        }
+        if self.trigram_weight != 0.0 {
+            if let Some(log_probability) = trigram_log_probability(candidate) {
+                score += self.trigram_weight * (log_probability - TRIGRAM_BASELINE);
+            }
+        }
+        score
+    }
+}
+
+/// Pearson's chi-squared statistic of the byte counts against English, counting each lower case
+/// letter on its own and upper case letters, white space, punctuation, digits and other symbols
+/// in one bucket each. Lower is more English.
+/// Unprintable bytes are left out, see `unprintable_fraction`.
+pub fn chi_squared(text: &[u8]) -> f64 {
+    let mut observed = [0usize; CATEGORIES];
+    for byte in text {
+        if let Some(category) = category(*byte) {
+            observed[category] += 1;
+        }
+    }
+    let total: usize = observed.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    observed
+        .iter()
+        .zip(expected_frequencies())
+        .map(|(observed, frequency)| {
+            let expected = frequency * total as f64;
+            let difference = *observed as f64 - expected;
+            difference * difference / expected
+        })
+        .sum()
+}
+
+/// The average log10 probability of the bytes in `text` under a model of English text that
+/// tells upper and lower case letters apart.
+pub fn unigram_log_probability(text: &[u8]) -> f64 {
+    if text.is_empty() {
+        return UNIGRAM_BASELINE;
+    }
+    let sum: f64 = text
+        .iter()
+        .map(|byte| byte_probability(*byte).log10())
+        .sum();
+    sum / text.len() as f64
+}
+
+fn byte_probability(byte: u8) -> f64 {
+    match category(byte) {
+        Some(letter) if letter < UPPER_CASE => {
+            LETTER_FREQUENCIES[letter].max(MIN_LETTER_FREQUENCY) * CATEGORY_SHARES[0]
+        }
+        Some(UPPER_CASE) => {
+            let letter = (byte.to_ascii_lowercase() - b'a') as usize;
+            LETTER_FREQUENCIES[letter].max(MIN_LETTER_FREQUENCY) * CATEGORY_SHARES[1]
+        }
+        Some(SPACE) if byte == b' ' => CATEGORY_SHARES[2],
+        Some(SPACE) => CATEGORY_SHARES[5],
+        Some(PUNCTUATION) => CATEGORY_SHARES[3] / 11.0,
+        Some(DIGIT) => CATEGORY_SHARES[4] / 10.0,
+        Some(_) => CATEGORY_SHARES[5] / 22.0,
+        None => UNPRINTABLE_PROBABILITY,
+    }
+}
+
+pub fn unprintable_fraction(text: &[u8]) -> f64 {
+    if text.is_empty() {
+        return 0.0;
+    }
+    text.iter().filter(|b| !is_printable(**b)).count() as f64 / text.len() as f64
+}
+
+pub fn is_printable(byte: u8) -> bool {
+    matches!(byte, b' '..=b'~' | b'\n' | b'\r' | b'\t')
+}
+
+/// The average log10 probability of the letter pairs in `text`, or `None` if it has none.
+pub fn bigram_log_probability(text: &[u8]) -> Option<f64> {
+    average_ngram_log_probability(text, 2, BIGRAMS, BIGRAM_FLOOR)
+}
+
+/// The average log10 probability of the letter triples in `text`, or `None` if it has none.
+pub fn trigram_log_probability(text: &[u8]) -> Option<f64> {
+    average_ngram_log_probability(text, 3, TRIGRAMS, TRIGRAM_FLOOR)
+}
+
+fn average_ngram_log_probability(
+    text: &[u8],
+    n: usize,
+    table: &[(&str, f64)],
+    floor: f64,
+) -> Option<f64> {
+    let (count, sum) = text
+        .windows(n)
+        .filter(|w| w.iter().all(u8::is_ascii_alphabetic))
+        .map(|w| {
+            let ngram = w.to_ascii_lowercase();
+            table
+                .iter()
+                .find(|(known, _frequency)| known.as_bytes() == ngram)
+                .map(|(_known, frequency)| frequency / 100.0)
+                .unwrap_or(floor)
+                .log10()
+        })
+        .fold((0, 0.0), |(count, sum), p| (count + 1, sum + p));
+    if count == 0 {
+        None
+    } else {
+        Some(sum / count as f64)
+    }
+}
+
+const CATEGORIES: usize = 31;
+const UPPER_CASE: usize = 26;
+const SPACE: usize = 27;
+const PUNCTUATION: usize = 28;
+const DIGIT: usize = 29;
+const SYMBOL: usize = 30;
+
+fn category(byte: u8) -> Option<usize> {
+    match byte {
+        b'a'..=b'z' => Some((byte - b'a') as usize),
+        b'A'..=b'Z' => Some(UPPER_CASE),
+        b' ' | b'\n' | b'\r' | b'\t' => Some(SPACE),
+        b'.' | b',' | b'\'' | b'"' | b'!' | b'?' | b'-' | b':' | b';' | b'(' | b')' => {
+            Some(PUNCTUATION)
+        }
+        b'0'..=b'9' => Some(DIGIT),
+        _ if is_printable(byte) => Some(SYMBOL),
+        _ => None,
+    }
+}
+
+/// The share of lower case letters, upper case letters, white space, punctuation, digits and
+/// other symbols in English text.
+const CATEGORY_SHARES: [f64; 6] = [0.745, 0.03, 0.19, 0.025, 0.008, 0.002];
+
+/// A floor for the expected letter frequencies, so that a single rare letter in a short text
+/// doesn't outweigh everything else.
+const MIN_LETTER_FREQUENCY: f64 = 0.006;
+
+fn expected_frequencies() -> [f64; CATEGORIES] {
+    let mut frequencies = [0.0; CATEGORIES];
+    for (frequency, letter) in frequencies.iter_mut().zip(LETTER_FREQUENCIES) {
+        *frequency = letter.max(MIN_LETTER_FREQUENCY) * CATEGORY_SHARES[0];
+    }
+    frequencies[UPPER_CASE..].copy_from_slice(&CATEGORY_SHARES[1..]);
+    frequencies
+}
+
+/// Relative frequencies of a to z in English text.
+pub const LETTER_FREQUENCIES: [f64; 26] = [
+    0.08167, 0.01492, 0.02782, 0.04253, 0.12702, 0.02228, 0.02015, 0.06094, 0.06966, 0.00153,
+    0.00772, 0.04025, 0.02406, 0.06749, 0.07507, 0.01929, 0.00095, 0.05987, 0.06327, 0.09056,
+    0.02758, 0.00978, 0.02360, 0.00150, 0.01974, 0.00074,
+];
+
+/// The most common English bigrams, in percent of all letter pairs.
+pub const BIGRAMS: &[(&str, f64)] = &[
+    ("th", 3.56),
+    ("he", 3.07),
+    ("in", 2.43),
+    ("er", 2.05),
+    ("an", 1.99),
+    ("re", 1.85),
+    ("on", 1.76),
+    ("at", 1.49),
+    ("en", 1.45),
+    ("nd", 1.35),
+    ("ti", 1.34),
+    ("es", 1.34),
+    ("or", 1.28),
+    ("te", 1.20),
+    ("of", 1.17),
+    ("ed", 1.17),
+    ("is", 1.13),
+    ("it", 1.12),
+    ("al", 1.09),
+    ("ar", 1.07),
+    ("st", 1.05),
+    ("to", 1.05),
+    ("nt", 1.04),
+    ("ng", 0.95),
+    ("se", 0.93),
+    ("ha", 0.93),
+    ("as", 0.87),
+    ("ou", 0.87),
+    ("io", 0.83),
+    ("le", 0.83),
+    ("ve", 0.83),
+    ("co", 0.79),
+    ("me", 0.79),
+    ("de", 0.76),
+    ("hi", 0.76),
+    ("ri", 0.73),
+    ("ro", 0.73),
+    ("ic", 0.70),
+    ("ne", 0.69),
+    ("ea", 0.69),
+    ("ra", 0.69),
+    ("ce", 0.65),
+    ("li", 0.62),
+    ("ch", 0.60),
+    ("ll", 0.58),
+    ("be", 0.58),
+    ("ma", 0.57),
+    ("si", 0.55),
+    ("om", 0.55),
+    ("ur", 0.54),
+];
+
+/// The most common English trigrams, in percent of all letter triples.
+pub const TRIGRAMS: &[(&str, f64)] = &[
+    ("the", 1.81),
+    ("and", 0.73),
+    ("ing", 0.72),
+    ("ent", 0.42),
+    ("ion", 0.42),
+    ("her", 0.36),
+    ("for", 0.34),
+    ("tha", 0.33),
+    ("nth", 0.33),
+    ("int", 0.32),
+    ("ere", 0.31),
+    ("tio", 0.31),
+    ("ter", 0.30),
+    ("est", 0.28),
+    ("ers", 0.28),
+    ("ati", 0.26),
+    ("hat", 0.26),
+    ("ate", 0.25),
+    ("all", 0.25),
+    ("eth", 0.24),
+    ("hes", 0.24),
+    ("ver", 0.24),
+    ("his", 0.24),
+    ("oft", 0.22),
+    ("ith", 0.21),
+    ("fth", 0.21),
+    ("sth", 0.21),
+    ("oth", 0.21),
+    ("res", 0.21),
+    ("ont", 0.20),
+];
+
+const UNPRINTABLE_PROBABILITY: f64 = 0.000001;
+
+/// The probability used for n-grams missing from the tables.
+const BIGRAM_FLOOR: f64 = 0.0005;
+const TRIGRAM_FLOOR: f64 = 0.00005;
+
+/// Roughly the average log probability of uniformly random letters, so that text without
+/// letters isn't preferred over text with uncommon n-grams.
+const UNIGRAM_BASELINE: f64 = -2.4;
+const BIGRAM_BASELINE: f64 = -3.0;
+const TRIGRAM_BASELINE: f64 = -4.2;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_chi_squared_prefers_english() {
+        let english = b"Now that the party is jumping";
+        let shuffled = b"Nqw thzt vhe pxrky iq jjmpikg";
+        assert!(chi_squared(english) < chi_squared(shuffled));
+    }
+
+    #[test]
+    fn test_unprintable_fraction() {
+        assert_eq!(0.0, unprintable_fraction(b"Cooking MC's\n"));
+        assert_eq!(0.5, unprintable_fraction(b"ab\x00\xff"));
+    }
+
+    #[test]
+    fn test_ngram_log_probabilities() {
+        let english = bigram_log_probability(b"the theory").unwrap();
+        let gibberish = bigram_log_probability(b"qzx vkjqz").unwrap();
+        assert!(english > gibberish);
+        assert!(trigram_log_probability(b"the") > trigram_log_probability(b"qzx"));
+        assert_eq!(None, bigram_log_probability(b"a b c"));
+    }
+
+    #[test]
+    fn test_english_scorer_works_on_raw_bytes() {
+        let scorer = EnglishScorer::default();
+        let english = b"Cooking MC's like a pound of bacon";
+        let high_bytes: Vec<u8> = english.iter().map(|b| b ^ 0x80).collect();
+        assert!(scorer.score(english) > scorer.score(&high_bytes));
+    }
+
+    const SHORT_TEXTS: &[u8] = b"It was the best of times, it was the worst of times, it was the \
+        age of wisdom, it was the age of foolishness. Call me Ishmael. Happy families are all \
+        alike; every unhappy family is unhappy in its own way. The quick brown fox jumps over the \
+        lazy dog. Meet me at the old bridge at noon tomorrow.";
+
+    const MIXED_TEXTS: &[u8] = b"ICE ICE BABY. Meet me at 10:30 on Platform 9, Track B! The NASA \
+        report (2021) says: \"Call 555-0199 NOW.\" Mr. O'Brien paid $45 for 3 tickets to New \
+        York; Dr. Smith's flight UA 921 leaves at 6:15 AM.";
+
+    /// How many `length` byte samples of `text` come out right when breaking single byte XOR by
+    /// picking the key `scorer` likes best, out of how many samples.
+    fn recovered_samples(
+        text: &[u8],
+        length: usize,
+        scorer: impl Fn(&[u8]) -> f64,
+    ) -> (usize, usize) {
+        let samples: Vec<&[u8]> = text.windows(length).step_by(3).collect();
+        let recovered = samples
+            .iter()
+            .filter(|sample| {
+                let best = (0..=255u8)
+                    .map(|key| sample.iter().map(|b| b ^ key).collect::<Vec<_>>())
+                    .max_by(|a, b| scorer(a).total_cmp(&scorer(b)))
+                    .unwrap();
+                best == **sample
+            })
+            .count();
+        (recovered, samples.len())
+    }
+
+    /// The vowel counting heuristic that set1::challenge3 used before the scorers.
+    fn vowel_heuristic(text: &[u8]) -> f64 {
+        let sum: isize = String::from_utf8_lossy(text)
+            .chars()
+            .map(|c| match c {
+                'a' | 'e' | 'i' | 'o' | 'u' | 'y' | ' ' => 25,
+                'a'..='z' => 10,
+                'A'..='Z' => 1,
+                _ => -50,
+            })
+            .sum();
+        sum as f64 / text.len() as f64
+    }
+
+    #[test]
+    fn test_english_scorer_on_short_texts() {
+        let scorer = EnglishScorer::default();
+        let (recovered, samples) = recovered_samples(SHORT_TEXTS, 10, |text| scorer.score(text));
+        assert!(recovered * 100 >= samples * 95);
+    }
+
+    #[test]
+    fn test_english_scorer_beats_vowel_heuristic() {
+        let scorer = EnglishScorer::default();
+        let (recovered, samples) = recovered_samples(MIXED_TEXTS, 16, |text| scorer.score(text));
+        let (recovered_by_heuristic, _) = recovered_samples(MIXED_TEXTS, 16, vowel_heuristic);
+        assert!(recovered * 100 >= samples * 90);
+        assert!(recovered > recovered_by_heuristic);
+    }
+}
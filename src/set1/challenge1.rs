@@ -1,30 +1,33 @@
-use anyhow::Result;
+use crate::error::CryptoError;
+use std::fmt;
 
 #[allow(dead_code)]
-pub fn hex_to_base64(hex: &str) -> Result<String> {
+pub fn hex_to_base64(hex: &str) -> Result<String, CryptoError> {
     let bin = hex_to_bin(hex)?;
     Ok(base64::encode(bin))
 }
 
 #[allow(dead_code)]
-pub fn hex_to_base64_homebrew(hex: &str) -> Result<String> {
-    Ok(hex_to_bin(hex)?
+pub fn hex_to_base64_homebrew(hex: &str) -> Result<String, CryptoError> {
+    let sextets = hex_to_bin(hex)?
         .chunks(3)
         .map(chunk_to_6bits)
-        .map(|x| {
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(sextets
+        .into_iter()
+        .flat_map(|x| {
             x.into_iter()
                 .map(|c| c.map(|c| BASE64_CHARS[c]).unwrap_or('='))
         })
-        .flatten()
         .collect())
 }
 
-fn chunk_to_6bits(chunk: &[u8]) -> Vec<Option<usize>> {
+fn chunk_to_6bits(chunk: &[u8]) -> Result<Vec<Option<usize>>, CryptoError> {
     return match *chunk {
-        [x] => vec![Some(a(x)), Some(a_rest(x)), None, None],
-        [x, y] => vec![Some(a(x)), Some(b(x, y)), Some(b_rest(y)), None],
-        [x, y, z] => vec![Some(a(x)), Some(b(x, y)), Some(c(y, z)), Some(d(z))],
-        _ => panic!("Invalid chunk length"),
+        [x] => Ok(vec![Some(a(x)), Some(a_rest(x)), None, None]),
+        [x, y] => Ok(vec![Some(a(x)), Some(b(x, y)), Some(b_rest(y)), None]),
+        [x, y, z] => Ok(vec![Some(a(x)), Some(b(x, y)), Some(c(y, z)), Some(d(z))]),
+        _ => Err(CryptoError::InvalidBlockSize(chunk.len())),
     };
 
     fn a(x: u8) -> usize {
@@ -52,7 +55,152 @@ fn chunk_to_6bits(chunk: &[u8]) -> Vec<Option<usize>> {
     }
 }
 
-fn hex_to_bin(hex: &str) -> Result<Vec<u8>> {
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Base64Alphabet {
+    Standard,
+    UrlSafe,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Base64Config {
+    pub alphabet: Base64Alphabet,
+    pub require_padding: bool,
+    /// Skip spaces, tabs and line breaks, as found in line wrapped MIME data.
+    pub ignore_whitespace: bool,
+}
+
+impl Base64Config {
+    pub const STANDARD: Base64Config = Base64Config {
+        alphabet: Base64Alphabet::Standard,
+        require_padding: true,
+        ignore_whitespace: false,
+    };
+    pub const URL_SAFE: Base64Config = Base64Config {
+        alphabet: Base64Alphabet::UrlSafe,
+        require_padding: false,
+        ignore_whitespace: false,
+    };
+    pub const MIME: Base64Config = Base64Config {
+        alphabet: Base64Alphabet::Standard,
+        require_padding: true,
+        ignore_whitespace: true,
+    };
+}
+
+/// All offsets are byte offsets into the input.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Base64DecodeError {
+    InvalidByte { offset: usize, byte: u8 },
+    InvalidLength { offset: usize },
+    InvalidPadding { offset: usize },
+    MissingPadding { offset: usize },
+    NonZeroTrailingBits { offset: usize },
+}
+
+impl fmt::Display for Base64DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Base64DecodeError::InvalidByte { offset, byte } => {
+                write!(f, "Invalid base64 byte {:#04x} at offset {}", byte, offset)
+            }
+            Base64DecodeError::InvalidLength { offset } => {
+                write!(
+                    f,
+                    "Base64 input ends with a lone symbol at offset {}",
+                    offset
+                )
+            }
+            Base64DecodeError::InvalidPadding { offset } => {
+                write!(f, "Misplaced base64 padding at offset {}", offset)
+            }
+            Base64DecodeError::MissingPadding { offset } => {
+                write!(f, "Missing base64 padding at offset {}", offset)
+            }
+            Base64DecodeError::NonZeroTrailingBits { offset } => write!(
+                f,
+                "The base64 symbol at offset {} has non-zero trailing bits",
+                offset
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Base64DecodeError {}
+
+pub fn base64_decode_homebrew(input: &str, config: Base64Config) -> Result<Vec<u8>, CryptoError> {
+    Ok(base64_decode(input, config)?)
+}
+
+fn base64_decode(input: &str, config: Base64Config) -> Result<Vec<u8>, Base64DecodeError> {
+    let mut symbols: Vec<(usize, u8)> = vec![];
+    let mut padding: Vec<usize> = vec![];
+    for (offset, byte) in input.bytes().enumerate() {
+        if config.ignore_whitespace && matches!(byte, b' ' | b'\t' | b'\r' | b'\n') {
+            continue;
+        }
+        if byte == b'=' {
+            if padding.len() == 2 {
+                return Err(Base64DecodeError::InvalidPadding { offset });
+            }
+            padding.push(offset);
+            continue;
+        }
+        let value = base64_value(byte, config.alphabet)
+            .ok_or(Base64DecodeError::InvalidByte { offset, byte })?;
+        if let Some(&padding_offset) = padding.first() {
+            return Err(Base64DecodeError::InvalidPadding {
+                offset: padding_offset,
+            });
+        }
+        symbols.push((offset, value));
+    }
+
+    let missing_symbols = (4 - symbols.len() % 4) % 4;
+    if let Some(&(offset, _)) = symbols.last() {
+        if missing_symbols == 3 {
+            return Err(Base64DecodeError::InvalidLength { offset });
+        }
+    }
+    if !padding.is_empty() && padding.len() != missing_symbols {
+        return Err(Base64DecodeError::InvalidPadding { offset: padding[0] });
+    }
+    if config.require_padding && padding.len() != missing_symbols {
+        return Err(Base64DecodeError::MissingPadding {
+            offset: input.len(),
+        });
+    }
+
+    let mut result = Vec::with_capacity(symbols.len() * 3 / 4);
+    for chunk in symbols.chunks(4) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (i, (_offset, value))| {
+                bits | (*value as u32) << (18 - 6 * i)
+            });
+        let bytes = bits.to_be_bytes();
+        let byte_count = chunk.len() - 1;
+        if bytes[1 + byte_count..].iter().any(|b| *b != 0) {
+            let (offset, _value) = chunk[chunk.len() - 1];
+            return Err(Base64DecodeError::NonZeroTrailingBits { offset });
+        }
+        result.extend(&bytes[1..1 + byte_count]);
+    }
+    Ok(result)
+}
+
+fn base64_value(byte: u8, alphabet: Base64Alphabet) -> Option<u8> {
+    match (byte, alphabet) {
+        (b'A'..=b'Z', _) => Some(byte - b'A'),
+        (b'a'..=b'z', _) => Some(byte - b'a' + 26),
+        (b'0'..=b'9', _) => Some(byte - b'0' + 52),
+        (b'+', Base64Alphabet::Standard) | (b'-', Base64Alphabet::UrlSafe) => Some(62),
+        (b'/', Base64Alphabet::Standard) | (b'_', Base64Alphabet::UrlSafe) => Some(63),
+        _ => None,
+    }
+}
+
+fn hex_to_bin(hex: &str) -> Result<Vec<u8>, CryptoError> {
     Ok(hex::decode(hex)?)
 }
 
@@ -66,6 +214,7 @@ static BASE64_CHARS: &[char] = &[
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::Rng;
 
     #[test]
     fn test_hex_to_bin() {
@@ -121,4 +270,93 @@ mod tests {
 
         assert_eq!(expected.to_string(), hex_to_base64_homebrew(input).unwrap());
     }
+
+    #[test]
+    fn test_decode_homebrew() {
+        let input = "SSdtIGtpbGxpbmcgeW91ciBicmFpbiBsaWtlIGEgcG9pc29ub3VzIG11c2hybw==";
+        let expected = "I'm killing your brain like a poisonous mushro";
+        let decoded = base64_decode_homebrew(input, Base64Config::STANDARD).unwrap();
+        assert_eq!(expected.as_bytes(), decoded);
+    }
+
+    #[test]
+    fn test_decode_homebrew_agrees_with_base64_crate() {
+        let mut rng = rand::thread_rng();
+        for len in 0..100 {
+            let data: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let standard = base64::encode(&data);
+            assert_eq!(
+                base64::decode(&standard).unwrap(),
+                base64_decode_homebrew(&standard, Base64Config::STANDARD).unwrap()
+            );
+            let url_safe = base64::encode_config(&data, base64::URL_SAFE_NO_PAD);
+            assert_eq!(
+                base64::decode_config(&url_safe, base64::URL_SAFE_NO_PAD).unwrap(),
+                base64_decode_homebrew(&url_safe, Base64Config::URL_SAFE).unwrap()
+            );
+            let url_safe_padded = base64::encode_config(&data, base64::URL_SAFE);
+            assert_eq!(
+                data,
+                base64_decode_homebrew(&url_safe_padded, Base64Config::URL_SAFE).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_homebrew_mime() {
+        let input = include_str!("6.txt");
+        let stripped: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+        assert_eq!(
+            base64::decode(stripped).unwrap(),
+            base64_decode_homebrew(input, Base64Config::MIME).unwrap()
+        );
+        assert_eq!(
+            Err(CryptoError::Base64(Base64DecodeError::InvalidByte {
+                offset: 4,
+                byte: b'\n'
+            })),
+            base64_decode_homebrew("SSdt\nIGtp", Base64Config::STANDARD)
+        );
+    }
+
+    #[test]
+    fn test_decode_homebrew_errors() {
+        assert_eq!(
+            Err(CryptoError::Base64(Base64DecodeError::InvalidByte {
+                offset: 2,
+                byte: b'-'
+            })),
+            base64_decode_homebrew("SS-t", Base64Config::STANDARD)
+        );
+        assert_eq!(
+            Err(CryptoError::Base64(Base64DecodeError::InvalidLength {
+                offset: 4
+            })),
+            base64_decode_homebrew("SSdtI", Base64Config::URL_SAFE)
+        );
+        assert_eq!(
+            Err(CryptoError::Base64(Base64DecodeError::MissingPadding {
+                offset: 6
+            })),
+            base64_decode_homebrew("SSdtIG", Base64Config::STANDARD)
+        );
+        assert_eq!(
+            Err(CryptoError::Base64(Base64DecodeError::InvalidPadding {
+                offset: 6
+            })),
+            base64_decode_homebrew("SSdtIG=", Base64Config::STANDARD)
+        );
+        assert_eq!(
+            Err(CryptoError::Base64(Base64DecodeError::InvalidPadding {
+                offset: 2
+            })),
+            base64_decode_homebrew("SS==SSdt", Base64Config::STANDARD)
+        );
+        assert_eq!(
+            Err(CryptoError::Base64(
+                Base64DecodeError::NonZeroTrailingBits { offset: 5 }
+            )),
+            base64_decode_homebrew("SSdtIH==", Base64Config::STANDARD)
+        );
+    }
 }
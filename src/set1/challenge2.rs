@@ -1,20 +1,24 @@
-use anyhow::{Context, Result};
+use crate::bits;
+use crate::error::CryptoError;
 
 #[allow(dead_code)]
-pub fn fixed_xor(l: &[u8], r: &[u8]) -> Vec<u8> {
+pub fn fixed_xor(l: &[u8], r: &[u8]) -> Result<Vec<u8>, CryptoError> {
     if l.len() != r.len() {
-        panic!("The inputs to fixed xor should have the same size");
+        return Err(CryptoError::LengthMismatch {
+            left: l.len(),
+            right: r.len(),
+        });
     }
 
-    l.iter().zip(r.iter()).map(|(l, r)| l ^ r).collect()
+    Ok(bits::xor(l, r))
 }
 
 #[allow(dead_code)]
-pub fn fixed_xor_hex(l: &str, r: &str) -> Result<String> {
-    let l = hex::decode(l).context("Decoding left hex string")?;
-    let r = hex::decode(r).context("Decoding right hex string")?;
-    let result = fixed_xor(&l, &r);
-    Ok(hex::encode(&result))
+pub fn fixed_xor_hex(l: &str, r: &str) -> Result<String, CryptoError> {
+    let l = hex::decode(l)?;
+    let r = hex::decode(r)?;
+    let result = fixed_xor(&l, &r)?;
+    Ok(hex::encode(result))
 }
 
 #[cfg(test)]
@@ -28,4 +32,12 @@ mod tests {
         let expected = "746865206b696420646f6e277420706c6179";
         assert_eq!(expected, fixed_xor_hex(input1, input2).unwrap());
     }
+
+    #[test]
+    fn test_fixed_xor_length_mismatch() {
+        assert_eq!(
+            Err(CryptoError::LengthMismatch { left: 2, right: 3 }),
+            fixed_xor(&[1, 2], &[1, 2, 3])
+        );
+    }
 }
@@ -1,44 +1,84 @@
+use crate::error::CryptoError;
+use crate::language_model::LanguageModel;
+use crate::scoring::{EnglishScorer, Scorer};
+
+/// Scores with `LanguageModel::english()`. This used to be `EnglishScorer::DEFAULT`, which can
+/// pick a different key for short or unusual texts. Call `break_single_byte_xor_with` with it to
+/// keep the old behaviour.
 #[allow(dead_code)]
-pub fn break_single_byte_xor(ciphertext_bin: &[u8]) -> (u8, String) {
-    (0..255)
-        .map(|key| (key, try_decode(key, ciphertext_bin)))
-        .map(|(key, plain)| (rate(&plain), key, plain))
-        .max_by_key(|(score, _key, _plain)| *score)
-        .map(|(_score, key, plain)| (key, plain))
-        .unwrap()
+pub fn break_single_byte_xor(ciphertext_bin: &[u8]) -> Result<(u8, String), CryptoError> {
+    break_single_byte_xor_with(ciphertext_bin, LanguageModel::english())
+}
+
+pub fn break_single_byte_xor_with(
+    ciphertext_bin: &[u8],
+    scorer: &dyn Scorer,
+) -> Result<(u8, String), CryptoError> {
+    let best = best_single_byte_xor(ciphertext_bin, scorer)?;
+    Ok((
+        best.key,
+        String::from_utf8_lossy(&best.plaintext).to_string(),
+    ))
 }
 
+/// How English `message` looks, times 100. Higher is better.
+#[deprecated(note = "use a scoring::Scorer such as EnglishScorer::DEFAULT")]
 pub fn rate(message: &str) -> isize {
-    message.chars().map(rate_char).sum::<isize>() * 100 / message.len() as isize
+    (EnglishScorer::DEFAULT.score(message.as_bytes()) * 100.0).round() as isize
 }
 
-fn try_decode(key: u8, ciphertext: &[u8]) -> String {
-    let x: Vec<_> = ciphertext.iter().map(|c| c ^ key).collect();
-    String::from_utf8_lossy(&x).to_string()
+/// A possible key for a single byte XOR ciphertext, with the plaintext it gives.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub key: u8,
+    pub score: f64,
+    pub plaintext: Vec<u8>,
 }
 
-fn rate_char(c: char) -> isize {
-    if is_vowel(c) || c == ' ' {
-        25
-    } else if is_alpha(c) {
-        10
-    } else if is_upper_alpha(c) {
-        1
-    } else {
-        -50
+/// The `count` best keys out of all 256, best first.
+pub fn single_byte_xor_candidates(
+    ciphertext_bin: &[u8],
+    count: usize,
+    scorer: &dyn Scorer,
+) -> Result<Vec<Candidate>, CryptoError> {
+    if ciphertext_bin.is_empty() {
+        return Err(CryptoError::EmptyInput);
     }
+    let mut candidates: Vec<Candidate> = (0..=255)
+        .map(|key| {
+            let plaintext = try_decode(key, ciphertext_bin);
+            Candidate {
+                key,
+                score: scorer.score(&plaintext),
+                plaintext,
+            }
+        })
+        .collect();
+    candidates.sort_by(|l, r| r.score.total_cmp(&l.score));
+    candidates.truncate(count);
+    Ok(candidates)
 }
 
-fn is_vowel(c: char) -> bool {
-    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y')
+/// How much better the first candidate scored than the second. A small margin means the
+/// ciphertext is ambiguous and the best candidate may well be wrong.
+pub fn confidence_margin(candidates: &[Candidate]) -> Option<f64> {
+    match candidates {
+        [first, second, ..] => Some(first.score - second.score),
+        _ => None,
+    }
 }
 
-fn is_alpha(c: char) -> bool {
-    ('a'..='z').contains(&c)
+pub(crate) fn best_single_byte_xor(
+    ciphertext_bin: &[u8],
+    scorer: &dyn Scorer,
+) -> Result<Candidate, CryptoError> {
+    single_byte_xor_candidates(ciphertext_bin, 1, scorer)?
+        .pop()
+        .ok_or(CryptoError::EmptyInput)
 }
 
-fn is_upper_alpha(c: char) -> bool {
-    ('A'..='Z').contains(&c)
+fn try_decode(key: u8, ciphertext: &[u8]) -> Vec<u8> {
+    ciphertext.iter().map(|c| c ^ key).collect()
 }
 
 #[cfg(test)]
@@ -49,7 +89,31 @@ mod test {
     fn test_break_single_byte_xor() {
         let ciphertext_hex = "1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736";
         let ciphertext_bin = hex::decode(ciphertext_hex).unwrap();
-        let (_key, plaintext) = break_single_byte_xor(&ciphertext_bin);
+        let (_key, plaintext) = break_single_byte_xor(&ciphertext_bin).unwrap();
         assert_eq!("Cooking MC's like a pound of bacon", plaintext);
     }
+
+    #[test]
+    fn test_single_byte_xor_candidates() {
+        let ciphertext: Vec<u8> = b"Hello there".iter().map(|b| b ^ 0xff).collect();
+        let candidates =
+            single_byte_xor_candidates(&ciphertext, 3, LanguageModel::english()).unwrap();
+        assert_eq!(3, candidates.len());
+        assert_eq!(0xff, candidates[0].key);
+        assert_eq!(b"Hello there".to_vec(), candidates[0].plaintext);
+        assert!(candidates.windows(2).all(|w| w[0].score >= w[1].score));
+        assert!(confidence_margin(&candidates).unwrap() > 0.0);
+        assert_eq!(None, confidence_margin(&candidates[..1]));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_rate() {
+        assert!(rate("Cooking MC's like a pound of bacon") > rate("Cqqmkle\x00OA7s\x00nkmg\x00c"));
+    }
+
+    #[test]
+    fn test_break_single_byte_xor_empty_input() {
+        assert_eq!(Err(CryptoError::EmptyInput), break_single_byte_xor(&[]));
+    }
 }
@@ -1,14 +1,223 @@
-use crate::set1::challenge3::{break_single_byte_xor, rate};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::io::BufRead;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{mpsc, Arc, Mutex};
 
+use crate::error::CryptoError;
+use crate::language_model::LanguageModel;
+use crate::scoring::Scorer;
+use crate::set1::challenge3::{best_single_byte_xor, Candidate};
+
+/// Scores with the bundled English language model, pass `EnglishScorer::DEFAULT` to
+/// `detect_single_byte_xor_with` for the scoring used before it.
 #[allow(dead_code)]
-pub fn detect_single_byte_xor(candidates: &[Vec<u8>]) -> String {
-    candidates
+pub fn detect_single_byte_xor(candidates: &[Vec<u8>]) -> Result<String, CryptoError> {
+    detect_single_byte_xor_with(candidates, LanguageModel::english())
+}
+
+pub fn detect_single_byte_xor_with(
+    candidates: &[Vec<u8>],
+    scorer: &dyn Scorer,
+) -> Result<String, CryptoError> {
+    let broken = candidates
         .iter()
-        .map(|x| break_single_byte_xor(x))
-        .map(|(_key, plain)| (rate(&plain), plain))
-        .max_by_key(|x| x.0)
-        .unwrap()
-        .1
+        .filter(|x| !x.is_empty())
+        .map(|x| best_single_byte_xor(x, scorer))
+        .collect::<Result<Vec<_>, _>>()?;
+    broken
+        .into_iter()
+        .max_by(|l, r| l.score.total_cmp(&r.score))
+        .map(|best| String::from_utf8_lossy(&best.plaintext).to_string())
+        .ok_or(CryptoError::EmptyInput)
+}
+
+pub struct StreamingOptions<'a> {
+    /// How many of the best lines to keep.
+    pub top: usize,
+    pub threads: usize,
+    /// How many lines to hand to a thread at a time.
+    pub batch_size: usize,
+    pub scorer: &'a dyn Scorer,
+    /// Called with the number of lines broken so far.
+    pub progress: Option<&'a (dyn Fn(usize) + Sync)>,
+}
+
+impl Default for StreamingOptions<'static> {
+    fn default() -> Self {
+        StreamingOptions {
+            top: 10,
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            batch_size: 1024,
+            scorer: LanguageModel::english(),
+            progress: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    /// Counted from 1.
+    pub line_number: usize,
+    pub candidate: Candidate,
+}
+
+/// Orders detections from best to worst, so the worst is at the top of a `BinaryHeap`.
+struct WorstFirst(Detection);
+
+impl Ord for WorstFirst {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .0
+            .candidate
+            .score
+            .total_cmp(&self.0.candidate.score)
+            .then(self.0.line_number.cmp(&other.0.line_number))
+    }
+}
+
+impl PartialOrd for WorstFirst {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for WorstFirst {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for WorstFirst {}
+
+/// A line that couldn't be broken, e.g. because it isn't valid hex.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BadLine {
+    /// Counted from 1.
+    pub line_number: usize,
+    pub error: CryptoError,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamingReport {
+    /// The best lines, best first.
+    pub detections: Vec<Detection>,
+    /// The lines that were skipped, in line order.
+    pub bad_lines: Vec<BadLine>,
+}
+
+/// Reads hex encoded lines from `reader` and breaks them on `options.threads` threads. Only the
+/// `options.top` best lines are kept. Empty lines are skipped, and lines that can't be decoded or
+/// broken are reported in `bad_lines` while the scan goes on. Only reading from `reader` or a
+/// failing worker thread stops the scan.
+pub fn detect_single_byte_xor_streaming<R: BufRead>(
+    reader: R,
+    options: &StreamingOptions,
+) -> Result<StreamingReport, CryptoError> {
+    let threads = options.threads.max(1);
+    let batch_size = options.batch_size.max(1);
+    let (sender, receiver) = mpsc::sync_channel::<Vec<(usize, Vec<u8>)>>(2 * threads);
+    // Shared by the workers only, so sending fails instead of blocking if they all stop.
+    let receiver = Arc::new(Mutex::new(receiver));
+    let processed = AtomicUsize::new(0);
+
+    let (read_result, results) = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                let processed = &processed;
+                scope.spawn(move || {
+                    let mut heap = BinaryHeap::new();
+                    let mut bad_lines = vec![];
+                    loop {
+                        let batch = match receiver.lock() {
+                            Ok(receiver) => match receiver.recv() {
+                                Ok(batch) => batch,
+                                Err(_) => break,
+                            },
+                            Err(_) => return Err(CryptoError::WorkerFailed),
+                        };
+                        for (line_number, line) in &batch {
+                            match best_single_byte_xor(line, options.scorer) {
+                                Ok(candidate) => heap.push(WorstFirst(Detection {
+                                    line_number: *line_number,
+                                    candidate,
+                                })),
+                                Err(error) => bad_lines.push(BadLine {
+                                    line_number: *line_number,
+                                    error,
+                                }),
+                            }
+                            if heap.len() > options.top {
+                                heap.pop();
+                            }
+                        }
+                        let done =
+                            processed.fetch_add(batch.len(), AtomicOrdering::Relaxed) + batch.len();
+                        if let Some(progress) = options.progress {
+                            progress(done);
+                        }
+                    }
+                    Ok((heap, bad_lines))
+                })
+            })
+            .collect();
+        drop(receiver);
+        let read_result = read_batches(reader, batch_size, &sender);
+        drop(sender);
+        let results: Vec<_> = workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap_or(Err(CryptoError::WorkerFailed)))
+            .collect();
+        (read_result, results)
+    });
+
+    let mut detections = vec![];
+    let mut bad_lines = read_result?;
+    for result in results {
+        let (heap, worker_bad_lines) = result?;
+        detections.extend(heap);
+        bad_lines.extend(worker_bad_lines);
+    }
+    detections.sort();
+    detections.truncate(options.top);
+    bad_lines.sort_by_key(|bad| bad.line_number);
+    Ok(StreamingReport {
+        detections: detections.into_iter().map(|d| d.0).collect(),
+        bad_lines,
+    })
+}
+
+/// Sends the decoded lines to the workers and returns the lines that aren't valid hex.
+fn read_batches<R: BufRead>(
+    reader: R,
+    batch_size: usize,
+    sender: &mpsc::SyncSender<Vec<(usize, Vec<u8>)>>,
+) -> Result<Vec<BadLine>, CryptoError> {
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut bad_lines = vec![];
+    for (index, line) in reader.split(b'\n').enumerate() {
+        let line = line?;
+        let line = line.trim_ascii();
+        if line.is_empty() {
+            continue;
+        }
+        match hex::decode(line) {
+            Ok(bytes) => batch.push((index + 1, bytes)),
+            Err(e) => bad_lines.push(BadLine {
+                line_number: index + 1,
+                error: e.into(),
+            }),
+        }
+        if batch.len() == batch_size {
+            let full = std::mem::replace(&mut batch, Vec::with_capacity(batch_size));
+            sender.send(full).map_err(|_| CryptoError::WorkerFailed)?;
+        }
+    }
+    if !batch.is_empty() {
+        sender.send(batch).map_err(|_| CryptoError::WorkerFailed)?;
+    }
+    Ok(bad_lines)
 }
 
 #[cfg(test)]
@@ -22,7 +231,70 @@ mod test {
             .map(hex::decode)
             .map(|x| x.unwrap())
             .collect();
-        let plaintext = detect_single_byte_xor(&data);
+        let plaintext = detect_single_byte_xor(&data).unwrap();
         assert_eq!("Now that the party is jumping\n".to_string(), plaintext);
     }
+
+    #[test]
+    fn test_detect_single_byte_xor_streaming() {
+        let progress_calls = AtomicUsize::new(0);
+        let progress = |_done: usize| {
+            progress_calls.fetch_add(1, AtomicOrdering::Relaxed);
+        };
+        let options = StreamingOptions {
+            top: 3,
+            threads: 4,
+            batch_size: 16,
+            progress: Some(&progress),
+            ..StreamingOptions::default()
+        };
+        let report =
+            detect_single_byte_xor_streaming(include_str!("4.txt").as_bytes(), &options).unwrap();
+        assert!(report.bad_lines.is_empty());
+        let detections = report.detections;
+        assert_eq!(3, detections.len());
+        assert_eq!(171, detections[0].line_number);
+        assert_eq!(
+            b"Now that the party is jumping\n".to_vec(),
+            detections[0].candidate.plaintext
+        );
+        assert!(detections
+            .windows(2)
+            .all(|w| w[0].candidate.score >= w[1].candidate.score));
+        assert_eq!(
+            include_str!("4.txt").lines().count().div_ceil(16),
+            progress_calls.load(AtomicOrdering::Relaxed)
+        );
+    }
+
+    #[test]
+    fn test_detect_single_byte_xor_streaming_bad_input() {
+        let options = StreamingOptions::default();
+        let report =
+            detect_single_byte_xor_streaming(&b"00ff\n\nxyz\n1b37\nabc\n"[..], &options).unwrap();
+        let mut line_numbers: Vec<_> = report.detections.iter().map(|d| d.line_number).collect();
+        line_numbers.sort();
+        assert_eq!(vec![1, 4], line_numbers);
+        assert_eq!(
+            vec![3, 5],
+            report
+                .bad_lines
+                .iter()
+                .map(|bad| bad.line_number)
+                .collect::<Vec<_>>()
+        );
+        assert!(matches!(report.bad_lines[0].error, CryptoError::Hex(_)));
+        let report = detect_single_byte_xor_streaming(&b"\n\n"[..], &options).unwrap();
+        assert!(report.detections.is_empty());
+        assert!(report.bad_lines.is_empty());
+    }
+
+    #[test]
+    fn test_detect_single_byte_xor_empty_input() {
+        assert_eq!(Err(CryptoError::EmptyInput), detect_single_byte_xor(&[]));
+        assert_eq!(
+            Err(CryptoError::EmptyInput),
+            detect_single_byte_xor(&[vec![]])
+        );
+    }
 }
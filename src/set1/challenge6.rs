@@ -1,55 +1,244 @@
-#[cfg(debug_assertions)]
-use crate::plot;
-
 use crate::data::{Ciphertext, Key, Plaintext};
-use crate::set1::challenge3::break_single_byte_xor;
+use crate::error::CryptoError;
+use crate::key_length::{Combined, KeyLengthEstimator};
+use crate::language_model::LanguageModel;
+use crate::scoring::Scorer;
+use crate::set1::challenge3::{break_single_byte_xor_with, single_byte_xor_candidates, Candidate};
 use crate::set1::challenge5::repeating_key_xor_decrypt;
 
-const MAX_KEY_SIZE: usize = 60; // The maximum key size we'll attempt
-const CHUNKS_TO_COMPARE_AT_A_TIME: usize = 10;
+pub struct RepeatingKeyXorOptions<'a> {
+    pub min_key_size: usize,
+    pub max_key_size: usize,
+    pub key_length_estimator: &'a dyn KeyLengthEstimator,
+    /// How many of the most likely key sizes to fully decrypt and score.
+    pub key_size_candidates: usize,
+    /// Rates the transposed columns, so it only sees every key size'th byte.
+    pub column_scorer: &'a dyn Scorer,
+    /// Rates the complete plaintexts to rank the candidates.
+    pub plaintext_scorer: &'a dyn Scorer,
+    /// Plaintext known in advance, e.g. a file header. The key bytes under it are taken from
+    /// it directly and key sizes it contradicts are skipped.
+    pub known_plaintext: &'a [KnownPlaintext<'a>],
+}
 
-pub fn break_repeating_key_xor(ciphertext: &Ciphertext) -> (Key, Plaintext) {
-    let key_length_candidates = dbg!(find_key_length_candidates(ciphertext, 10));
-    let chosen_key_length = dbg!(key_length_candidates[0]);
-    let transposed_blocks = transpose_blocks(&ciphertext.0, chosen_key_length);
-    let key: Key = transposed_blocks
-        .iter()
-        .map(|tb| break_single_byte_xor(tb))
-        .map(|(key, _plain)| key)
-        .collect();
-    let plaintext = repeating_key_xor_decrypt(ciphertext, &key);
-    (key, plaintext)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnownPlaintext<'a> {
+    pub offset: usize,
+    pub bytes: &'a [u8],
+}
+
+/// Scores with the bundled English language model. Set `column_scorer` to
+/// `EnglishScorer::UNIGRAMS_ONLY` and `plaintext_scorer` to `EnglishScorer::DEFAULT` for the
+/// rankings, and scores, of the earlier defaults.
+impl Default for RepeatingKeyXorOptions<'static> {
+    fn default() -> Self {
+        RepeatingKeyXorOptions {
+            min_key_size: 2,
+            max_key_size: 60,
+            key_length_estimator: &Combined::ALL,
+            key_size_candidates: 10,
+            column_scorer: LanguageModel::english_unigrams(),
+            plaintext_scorer: LanguageModel::english(),
+            known_plaintext: &[],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatingKeyXorCandidate {
+    pub key: Key,
+    pub plaintext: Plaintext,
+    pub score: f64,
+}
+
+impl RepeatingKeyXorCandidate {
+    /// Replaces one byte of the key, e.g. when one column was broken wrong, and updates the
+    /// plaintext and score to match.
+    pub fn set_key_byte(
+        &mut self,
+        index: usize,
+        value: u8,
+        scorer: &dyn Scorer,
+    ) -> Result<(), CryptoError> {
+        let key_len = self.key.len();
+        let old = *self.key.0.get(index).ok_or(CryptoError::IndexOutOfRange {
+            index,
+            len: key_len,
+        })?;
+        for byte in self.plaintext.0.iter_mut().skip(index).step_by(key_len) {
+            *byte ^= old ^ value;
+        }
+        self.key.0[index] = value;
+        self.score = scorer.score(&self.plaintext.0);
+        Ok(())
+    }
+
+    /// The `count` best values for one byte of the key, to pick from with `set_key_byte`.
+    pub fn key_byte_alternatives(
+        &self,
+        index: usize,
+        count: usize,
+        scorer: &dyn Scorer,
+    ) -> Result<Vec<Candidate>, CryptoError> {
+        let key_len = self.key.len();
+        let old = *self.key.0.get(index).ok_or(CryptoError::IndexOutOfRange {
+            index,
+            len: key_len,
+        })?;
+        let column: Vec<u8> = self
+            .plaintext
+            .0
+            .iter()
+            .skip(index)
+            .step_by(key_len)
+            .map(|p| p ^ old)
+            .collect();
+        single_byte_xor_candidates(&column, count, scorer)
+    }
+}
+
+pub fn break_repeating_key_xor(ciphertext: &Ciphertext) -> Result<(Key, Plaintext), CryptoError> {
+    break_repeating_key_xor_with(ciphertext, LanguageModel::english_unigrams())
+}
+
+/// `scorer` rates the transposed columns, so it only sees every key length'th byte.
+pub fn break_repeating_key_xor_with(
+    ciphertext: &Ciphertext,
+    scorer: &dyn Scorer,
+) -> Result<(Key, Plaintext), CryptoError> {
+    let options = RepeatingKeyXorOptions {
+        column_scorer: scorer,
+        ..RepeatingKeyXorOptions::default()
+    };
+    let best = rank_repeating_key_xor(ciphertext, &options)?.remove(0);
+    Ok((best.key, best.plaintext))
 }
 
-fn find_key_length_candidates(ciphertext: &Ciphertext, number_of_candidates: usize) -> Vec<usize> {
-    let mut ks_scores: Vec<_> = (2..MAX_KEY_SIZE)
-        .map(|ks| ciphertext.0.chunks(ks).take(CHUNKS_TO_COMPARE_AT_A_TIME))
-        .map(|c| {
-            let v: Vec<_> = c.collect();
-            let len = v[0].len();
-            let hamming_values = multi_hamming(&v);
-            if hamming_values.is_empty() {
-                None
-            } else {
-                let mh: usize = hamming_values.iter().sum::<usize>() / hamming_values.len();
-                Some((len, mh))
+/// Breaks the ciphertext under each of the most likely key sizes and returns the results, best
+/// first. Key sizes that are multiples of the real key size give the same key repeated, these
+/// are reduced to the shortest period and only reported once.
+pub fn rank_repeating_key_xor(
+    ciphertext: &Ciphertext,
+    options: &RepeatingKeyXorOptions,
+) -> Result<Vec<RepeatingKeyXorCandidate>, CryptoError> {
+    for known in options.known_plaintext {
+        let end = known_position(known.offset, known.bytes.len())?;
+        if end > ciphertext.len() {
+            return Err(CryptoError::InputTooShort {
+                min_len: end,
+                len: ciphertext.len(),
+            });
+        }
+    }
+    let key_size_candidates = find_key_length_candidates(ciphertext, options);
+    if key_size_candidates.is_empty() {
+        return Err(CryptoError::InputTooShort {
+            min_len: 2 * options.min_key_size.max(1),
+            len: ciphertext.0.len(),
+        });
+    }
+    let mut candidates: Vec<RepeatingKeyXorCandidate> = vec![];
+    for key_size in key_size_candidates {
+        let pinned = match pin_key_bytes(ciphertext, key_size, options.known_plaintext)? {
+            Some(pinned) => pinned,
+            None => continue,
+        };
+        let key = reduce_to_period(break_key(ciphertext, &pinned, options.column_scorer)?);
+        if candidates.iter().any(|c| c.key == key) {
+            continue;
+        }
+        let plaintext = repeating_key_xor_decrypt(ciphertext, &key)?;
+        let score = options.plaintext_scorer.score(&plaintext.0);
+        candidates.push(RepeatingKeyXorCandidate {
+            key,
+            plaintext,
+            score,
+        });
+    }
+    if candidates.is_empty() {
+        return Err(CryptoError::InvalidParameter(
+            "known plaintext contradicts every key size",
+        ));
+    }
+    candidates.sort_by(|l, r| r.score.total_cmp(&l.score));
+    Ok(candidates)
+}
+
+/// The key bytes given by the known plaintext for a key of `key_size` bytes, or `None` if the
+/// known plaintext needs two different values for the same key byte.
+fn pin_key_bytes(
+    ciphertext: &Ciphertext,
+    key_size: usize,
+    known_plaintext: &[KnownPlaintext],
+) -> Result<Option<Vec<Option<u8>>>, CryptoError> {
+    let mut pinned = vec![None; key_size];
+    for known in known_plaintext {
+        for (index, byte) in known.bytes.iter().enumerate() {
+            let position = known_position(known.offset, index)?;
+            let key_byte = ciphertext[position] ^ byte;
+            match pinned[position % key_size] {
+                Some(other) if other != key_byte => return Ok(None),
+                _ => pinned[position % key_size] = Some(key_byte),
             }
-        })
-        .flatten()
-        .collect();
+        }
+    }
+    Ok(Some(pinned))
+}
 
-    #[cfg(debug_assertions)]
-    plot::simple_xy("score vs keysize", "ks_scores.png", &ks_scores);
+fn known_position(offset: usize, index: usize) -> Result<usize, CryptoError> {
+    offset
+        .checked_add(index)
+        .ok_or(CryptoError::InvalidParameter(
+            "known plaintext offset is too large",
+        ))
+}
 
-    ks_scores.sort_by_key(|(_ks, score)| *score);
-    ks_scores
+/// Breaks the key bytes that aren't pinned column by column.
+fn break_key(
+    ciphertext: &Ciphertext,
+    pinned: &[Option<u8>],
+    scorer: &dyn Scorer,
+) -> Result<Key, CryptoError> {
+    transpose_blocks(&ciphertext.0, pinned.len())
         .iter()
-        .take(number_of_candidates)
-        .map(|x| x.0)
+        .zip(pinned)
+        .map(|(tb, pinned)| match pinned {
+            Some(key) => Ok(*key),
+            None => break_single_byte_xor_with(tb, scorer).map(|(key, _plain)| key),
+        })
         .collect()
 }
 
-fn transpose_blocks(ciphertext: &[u8], keysize: usize) -> Vec<Vec<u8>> {
+/// Shortens a key that consists of a shorter key repeated, which is what breaking under a
+/// multiple of the real key size gives.
+fn reduce_to_period(key: Key) -> Key {
+    let bytes = key.as_bytes();
+    let period = (1..bytes.len())
+        .filter(|period| bytes.len().is_multiple_of(*period))
+        .find(|period| {
+            bytes
+                .chunks(*period)
+                .all(|chunk| chunk == &bytes[..*period])
+        });
+    match period {
+        Some(period) => key[..period].iter().copied().collect(),
+        None => key,
+    }
+}
+
+fn find_key_length_candidates(
+    ciphertext: &Ciphertext,
+    options: &RepeatingKeyXorOptions,
+) -> Vec<usize> {
+    let mut candidates = options.key_length_estimator.rank(
+        &ciphertext.0,
+        options.min_key_size.max(1)..=options.max_key_size,
+    );
+    candidates.truncate(options.key_size_candidates);
+    candidates
+}
+
+pub(crate) fn transpose_blocks(ciphertext: &[u8], keysize: usize) -> Vec<Vec<u8>> {
     let mut result = Vec::<Vec<u8>>::new();
     result.resize(keysize, vec![]);
     for block in ciphertext.chunks(keysize) {
@@ -60,36 +249,11 @@ fn transpose_blocks(ciphertext: &[u8], keysize: usize) -> Vec<Vec<u8>> {
     result
 }
 
-fn multi_hamming(data: &[&[u8]]) -> Vec<usize> {
-    let len = data[0].len();
-    let mut data = data.iter();
-    let base = data.next().unwrap();
-    data.filter(|d| d.len() == len)
-        .map(|d| {
-            d.len();
-            base.len();
-            hamming_distance(base, d) * 100 / len
-        })
-        .collect()
-}
-
-fn hamming_distance(s1: &[u8], s2: &[u8]) -> usize {
-    s1.iter()
-        .zip(s2.iter())
-        .map(|(a, b)| differing_bits(*a, *b))
-        .sum()
-}
-
-fn differing_bits(a: u8, b: u8) -> usize {
-    (0..7)
-        .map(|x| (1 << x) & (a ^ b))
-        .filter(|x| *x != 0)
-        .count()
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::key_length::IndexOfCoincidence;
+    use crate::set1::challenge1::{base64_decode_homebrew, Base64Config};
     use crate::set1::challenge5::repeating_key_xor_encrypt;
     use std::str::FromStr;
 
@@ -97,22 +261,16 @@ mod test {
     fn test_find_key_length() {
         let key = Key::from_str("{}/!!@#$axcss").unwrap();
         let plaintext = Plaintext::from_str("This is my testing plaintext which is a very plain text but also a plaintext which is used to test my code.").unwrap();
-        let ciphertext = repeating_key_xor_encrypt(&plaintext, &key);
-        let number_of_candidates = 2;
-        let found_candidates = find_key_length_candidates(&ciphertext, number_of_candidates);
-        dbg!(&found_candidates);
-        assert_eq!(found_candidates.len(), number_of_candidates);
+        let ciphertext = repeating_key_xor_encrypt(&plaintext, &key).unwrap();
+        let options = RepeatingKeyXorOptions {
+            key_size_candidates: 2,
+            ..RepeatingKeyXorOptions::default()
+        };
+        let found_candidates = find_key_length_candidates(&ciphertext, &options);
+        assert_eq!(found_candidates.len(), options.key_size_candidates);
         assert!(found_candidates.contains(&key.0.len()));
     }
 
-    #[test]
-    fn test_hamming_distance() {
-        assert_eq!(
-            37,
-            hamming_distance("this is a test".as_bytes(), "wokka wokka!!!".as_bytes())
-        )
-    }
-
     #[test]
     fn test_transpose_blocks() {
         let input = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
@@ -134,12 +292,142 @@ mod test {
 
     #[test]
     fn test_break_repeating_key_xor() {
-        let ciphertext_base64: String = include_str!("6.txt")
-            .chars()
-            .filter(|c| *c != '\n')
-            .collect();
-        let ciphertext = Ciphertext(base64::decode(ciphertext_base64).unwrap());
-        let (key, _plaintext) = break_repeating_key_xor(&ciphertext);
-        assert_eq!("Terminator X: Bring the noise", key.to_string());
+        let ciphertext =
+            Ciphertext(base64_decode_homebrew(include_str!("6.txt"), Base64Config::MIME).unwrap());
+        let (key, _plaintext) = break_repeating_key_xor(&ciphertext).unwrap();
+        assert_eq!("Terminator X: Bring the noise", key.reveal().to_string());
+    }
+
+    #[test]
+    fn test_rank_repeating_key_xor() {
+        let ciphertext =
+            Ciphertext(base64_decode_homebrew(include_str!("6.txt"), Base64Config::MIME).unwrap());
+        let candidates =
+            rank_repeating_key_xor(&ciphertext, &RepeatingKeyXorOptions::default()).unwrap();
+        assert_eq!(
+            "Terminator X: Bring the noise",
+            candidates[0].key.reveal().to_string()
+        );
+        assert!(candidates.windows(2).all(|w| w[0].score >= w[1].score));
+        assert!(candidates
+            .iter()
+            .skip(1)
+            .all(|c| c.key != candidates[0].key));
+    }
+
+    #[test]
+    fn test_rank_repeating_key_xor_with_estimator() {
+        let ciphertext =
+            Ciphertext(base64_decode_homebrew(include_str!("6.txt"), Base64Config::MIME).unwrap());
+        let options = RepeatingKeyXorOptions {
+            key_length_estimator: &IndexOfCoincidence,
+            key_size_candidates: 1,
+            ..RepeatingKeyXorOptions::default()
+        };
+        let candidates = rank_repeating_key_xor(&ciphertext, &options).unwrap();
+        assert_eq!(
+            "Terminator X: Bring the noise",
+            candidates[0].key.reveal().to_string()
+        );
+    }
+
+    #[test]
+    fn test_known_plaintext() {
+        let key = Key::from_str("Secret").unwrap();
+        let plaintext =
+            Plaintext::from_str("PK\x03\x04 followed by a short text that is too short").unwrap();
+        let ciphertext = repeating_key_xor_encrypt(&plaintext, &key).unwrap();
+        let known = [KnownPlaintext {
+            offset: 0,
+            bytes: b"PK\x03\x04",
+        }];
+        let options = RepeatingKeyXorOptions {
+            known_plaintext: &known,
+            ..RepeatingKeyXorOptions::default()
+        };
+        let candidates = rank_repeating_key_xor(&ciphertext, &options).unwrap();
+        assert!(candidates.iter().all(|c| c.key[..4] == key[..4]));
+        assert_eq!(key, candidates[0].key);
+
+        let known = [KnownPlaintext {
+            offset: ciphertext.len() - 1,
+            bytes: b"tx",
+        }];
+        let options = RepeatingKeyXorOptions {
+            known_plaintext: &known,
+            ..RepeatingKeyXorOptions::default()
+        };
+        assert!(rank_repeating_key_xor(&ciphertext, &options).is_err());
+
+        let known = [
+            KnownPlaintext {
+                offset: 0,
+                bytes: b"P",
+            },
+            KnownPlaintext {
+                offset: 0,
+                bytes: b"Q",
+            },
+        ];
+        let options = RepeatingKeyXorOptions {
+            known_plaintext: &known,
+            ..RepeatingKeyXorOptions::default()
+        };
+        assert_eq!(
+            Err(CryptoError::InvalidParameter(
+                "known plaintext contradicts every key size"
+            )),
+            rank_repeating_key_xor(&ciphertext, &options)
+        );
+
+        let known = [KnownPlaintext {
+            offset: usize::MAX,
+            bytes: b"tx",
+        }];
+        let options = RepeatingKeyXorOptions {
+            known_plaintext: &known,
+            ..RepeatingKeyXorOptions::default()
+        };
+        assert_eq!(
+            Err(CryptoError::InvalidParameter(
+                "known plaintext offset is too large"
+            )),
+            rank_repeating_key_xor(&ciphertext, &options)
+        );
+    }
+
+    #[test]
+    fn test_set_key_byte() {
+        let key = Key::from_str("ICE").unwrap();
+        let plaintext = Plaintext::from_str("Burning 'em, if you ain't quick and nimble").unwrap();
+        let ciphertext = repeating_key_xor_encrypt(&plaintext, &key).unwrap();
+        let mut candidate = RepeatingKeyXorCandidate {
+            key: Key::from_str("IXE").unwrap(),
+            plaintext: repeating_key_xor_decrypt(&ciphertext, &Key::from_str("IXE").unwrap())
+                .unwrap(),
+            score: 0.0,
+        };
+        let scorer = LanguageModel::english();
+        let alternatives = candidate.key_byte_alternatives(1, 3, scorer).unwrap();
+        assert_eq!(b'C', alternatives[0].key);
+        candidate.set_key_byte(1, b'C', scorer).unwrap();
+        assert_eq!(key, candidate.key);
+        assert_eq!(plaintext, candidate.plaintext);
+        assert_eq!(scorer.score(&plaintext.0), candidate.score);
+        assert!(candidate.set_key_byte(3, 0, scorer).is_err());
+    }
+
+    #[test]
+    fn test_reduce_to_period() {
+        let key = Key::from_str("ICEICEICE").unwrap();
+        assert_eq!(Key::from_str("ICE").unwrap(), reduce_to_period(key));
+        let key = Key::from_str("ICEICEIC").unwrap();
+        assert_eq!(Key::from_str("ICEICEIC").unwrap(), reduce_to_period(key));
+    }
+
+    #[test]
+    fn test_break_repeating_key_xor_too_short() {
+        assert!(break_repeating_key_xor(&Ciphertext(vec![])).is_err());
+        assert!(break_repeating_key_xor(&Ciphertext(vec![1, 2, 3])).is_err());
     }
 }
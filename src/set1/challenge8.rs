@@ -1,26 +1,144 @@
 use crate::data::Ciphertext;
+use crate::error::CryptoError;
 use std::collections::HashMap;
+use std::ops::Range;
 
-#[allow(dead_code)]
-fn find_ciphertext_with_most_repetitions(
+pub fn find_ciphertext_with_most_repetitions(
     block_size: usize,
     ciphertexts: &[Ciphertext],
-) -> &Ciphertext {
-    ciphertexts
+) -> Result<&Ciphertext, CryptoError> {
+    let repetitions = ciphertexts
         .iter()
-        .map(|ct| (ct, count_repetitions(block_size, &ct.0)))
+        .filter(|ct| !ct.0.is_empty())
+        .map(|ct| count_repetitions(block_size, &ct.0).map(|r| (ct, r)))
+        .collect::<Result<Vec<_>, _>>()?;
+    repetitions
+        .into_iter()
         .map(|(ct, r)| (ct, r.values().sum::<usize>() * 100 / r.len()))
         .max_by_key(|(_ct, x)| *x)
-        .unwrap()
-        .0
+        .map(|(ct, _x)| ct)
+        .ok_or(CryptoError::EmptyInput)
 }
 
-pub fn count_repetitions(block_size: usize, data: &[u8]) -> HashMap<&[u8], usize> {
+pub fn count_repetitions(
+    block_size: usize,
+    data: &[u8],
+) -> Result<HashMap<&[u8], usize>, CryptoError> {
+    if block_size == 0 {
+        return Err(CryptoError::InvalidBlockSize(block_size));
+    }
     let mut result = HashMap::new();
     for c in data.chunks(block_size) {
         *result.entry(c).or_insert(0) += 1;
     }
-    result
+    Ok(result)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EcbScanOptions<'a> {
+    pub block_sizes: &'a [usize],
+    /// Skip blocks of a single repeated byte, such as the zero padding in binaries, since ECB
+    /// hardly ever produces them while plaintext often does.
+    pub skip_uniform_blocks: bool,
+}
+
+impl Default for EcbScanOptions<'static> {
+    fn default() -> Self {
+        EcbScanOptions {
+            block_sizes: &[8, 16],
+            skip_uniform_blocks: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepeatedBlock {
+    pub block: Vec<u8>,
+    pub offsets: Vec<usize>,
+}
+
+/// The blocks that repeat when `data` is split into `block_size` blocks starting at
+/// `alignment`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EcbFinding {
+    pub block_size: usize,
+    pub alignment: usize,
+    pub repeated: Vec<RepeatedBlock>,
+    /// The fraction of the blocks that are repeats of an earlier block.
+    pub score: f64,
+}
+
+impl EcbFinding {
+    /// From the first to the end of the last repeated block, which is where the ECB encrypted
+    /// part of a larger file is.
+    pub fn region(&self) -> Range<usize> {
+        let offsets = self.repeated.iter().flat_map(|r| r.offsets.iter());
+        let start = offsets.clone().min().copied().unwrap_or(self.alignment);
+        let end = offsets.max().map_or(start, |o| o + self.block_size);
+        start..end
+    }
+}
+
+/// Looks for repeated blocks at every alignment of every block size, best finding first. Only
+/// alignments where some block repeats are reported.
+pub fn scan_for_ecb(data: &[u8], options: &EcbScanOptions) -> Result<Vec<EcbFinding>, CryptoError> {
+    let mut findings = vec![];
+    for &block_size in options.block_sizes {
+        if block_size == 0 {
+            return Err(CryptoError::InvalidBlockSize(block_size));
+        }
+        for alignment in 0..block_size.min(data.len()) {
+            if let Some(finding) = scan_alignment(data, block_size, alignment, options) {
+                findings.push(finding);
+            }
+        }
+    }
+    findings.sort_by(|l, r| {
+        r.score
+            .total_cmp(&l.score)
+            .then(r.block_size.cmp(&l.block_size))
+            .then(l.alignment.cmp(&r.alignment))
+    });
+    Ok(findings)
+}
+
+fn scan_alignment(
+    data: &[u8],
+    block_size: usize,
+    alignment: usize,
+    options: &EcbScanOptions,
+) -> Option<EcbFinding> {
+    let blocks = data[alignment..].chunks_exact(block_size);
+    let block_count = blocks.len();
+    let mut offsets: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    for (index, block) in blocks.enumerate() {
+        if options.skip_uniform_blocks && block.iter().all(|b| *b == block[0]) {
+            continue;
+        }
+        offsets
+            .entry(block)
+            .or_default()
+            .push(alignment + index * block_size);
+    }
+    let mut repeated: Vec<RepeatedBlock> = offsets
+        .into_iter()
+        .filter(|(_, offsets)| offsets.len() > 1)
+        .map(|(block, offsets)| RepeatedBlock {
+            block: block.to_vec(),
+            offsets,
+        })
+        .collect();
+    if repeated.is_empty() {
+        return None;
+    }
+    repeated.sort_by_key(|r| (std::cmp::Reverse(r.offsets.len()), r.offsets[0]));
+    let repeats: usize = repeated.iter().map(|r| r.offsets.len() - 1).sum();
+    Some(EcbFinding {
+        block_size,
+        alignment,
+        repeated,
+        score: repeats as f64 / block_count as f64,
+    })
 }
 
 #[cfg(test)]
@@ -32,11 +150,66 @@ mod test {
         let input = include_str!("8.txt");
         let inputs = input
             .lines()
-            .map(|x| hex::decode(x))
+            .map(hex::decode)
             .filter_map(Result::ok)
-            .map(|i| Ciphertext(i));
+            .map(Ciphertext);
         let inputs = inputs.collect::<Vec<Ciphertext>>();
-        let ecb_encrypted = find_ciphertext_with_most_repetitions(16, &inputs);
+        let ecb_encrypted = find_ciphertext_with_most_repetitions(16, &inputs).unwrap();
         assert_eq!(ecb_encrypted.0, inputs[132].0); // 132 has been checked against other peoples solutions.
     }
+
+    #[test]
+    fn test_count_repetitions_zero_block_size() {
+        assert_eq!(
+            Err(CryptoError::InvalidBlockSize(0)),
+            count_repetitions(0, &[1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn test_scan_for_ecb_finds_embedded_region() {
+        let input = include_str!("8.txt");
+        let ecb = hex::decode(input.lines().nth(132).unwrap()).unwrap();
+        let mut data = vec![0; 21];
+        data.extend((0..100u8).map(|i| i.wrapping_mul(37)));
+        let start = data.len();
+        data.extend(&ecb);
+        data.extend(vec![0; 64]);
+
+        let findings = scan_for_ecb(&data, &EcbScanOptions::default()).unwrap();
+        let best = &findings[0];
+        assert_eq!(16, best.block_size);
+        assert_eq!(start % 16, best.alignment);
+        assert_eq!(4, best.repeated[0].offsets.len());
+        assert_eq!(start + 16, best.region().start);
+        assert!(best.region().end <= start + ecb.len());
+        assert!(findings
+            .iter()
+            .all(|f| f.block_size == 8 || f.alignment == best.alignment));
+    }
+
+    #[test]
+    fn test_scan_for_ecb_without_repetitions() {
+        let data: Vec<u8> = (0..=255).collect();
+        assert_eq!(
+            Vec::<EcbFinding>::new(),
+            scan_for_ecb(&data, &EcbScanOptions::default()).unwrap()
+        );
+        let options = EcbScanOptions {
+            skip_uniform_blocks: false,
+            ..EcbScanOptions::default()
+        };
+        let zeros = scan_for_ecb(&[0; 48], &options).unwrap();
+        assert_eq!((8, 5.0 / 6.0), (zeros[0].block_size, zeros[0].score));
+        assert_eq!(
+            Err(CryptoError::InvalidBlockSize(0)),
+            scan_for_ecb(
+                &data,
+                &EcbScanOptions {
+                    block_sizes: &[0],
+                    ..options
+                }
+            )
+        );
+    }
 }
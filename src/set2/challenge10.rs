@@ -1,70 +1,109 @@
-use aes::cipher::{BlockDecryptMut, BlockEncryptMut};
-use aes::NewBlockCipher;
-use anyhow::Result;
-
+use aes::Aes128;
 
 use crate::data::{Ciphertext, Iv, Key, Plaintext};
-use crate::set1::challenge2::fixed_xor;
-use crate::set2::challenge9::pkcs7_pad;
+use crate::error::CryptoError;
+use crate::modes::{cbc_decrypt, cbc_encrypt};
+use crate::set2::challenge9::Padding;
 
+pub fn aes128_cbc_decrypt(
+    ciphertext: &Ciphertext,
+    key: &Key,
+    iv: Iv,
+) -> Result<Plaintext, CryptoError> {
+    aes128_cbc_decrypt_with_padding(ciphertext, key, iv, Padding::Pkcs7)
+}
 
-pub fn aes128_cbc_decrypt(ciphertext: &Ciphertext, key: &Key, mut iv: Iv) -> Result<Plaintext> {
-    let mut plaintext: Vec<u8> = vec![];
-    let mut cipher = aes::Aes128::new_from_slice(&key.0).unwrap();
-    for ciphertext_chunk in ciphertext.0.chunks(16) {
-        let next_iv = ciphertext_chunk.to_vec();
-        let mut chunk = *aes::cipher::generic_array::GenericArray::from_slice(ciphertext_chunk);
-        cipher.decrypt_block_mut(&mut chunk);
-        let plaintext_chunk = fixed_xor(&chunk, &iv.0);
-        iv.0 = next_iv;
-        plaintext.extend(&plaintext_chunk);
-    }
-    Ok(Plaintext(plaintext)) // TODO: de-pad
+pub fn aes128_cbc_decrypt_with_padding(
+    ciphertext: &Ciphertext,
+    key: &Key,
+    iv: Iv,
+    padding: Padding,
+) -> Result<Plaintext, CryptoError> {
+    cbc_decrypt::<Aes128>(ciphertext, key, &iv, padding)
 }
 
-pub fn aes128_cbc_encrypt(plaintext: &Plaintext, key: &Key, mut iv: Iv) -> Result<Ciphertext> {
-    let plaintext = pkcs7_pad(&plaintext, 16);
-    let mut ciphertext: Vec<u8> = vec![];
-    let mut cipher = aes::Aes128::new_from_slice(&key.0).unwrap();
-    for plaintext_chunk in plaintext.0.chunks(16) {
-        let xored_chunk = fixed_xor(plaintext_chunk, &iv.0);
-        let mut chunk = *aes::cipher::generic_array::GenericArray::from_slice(&xored_chunk);
-        cipher.encrypt_block_mut(&mut chunk);
-        iv.0 = chunk.to_vec();
-        ciphertext.extend(&chunk);
-    }
-    Ok(Ciphertext(ciphertext))
+pub fn aes128_cbc_encrypt(
+    plaintext: &Plaintext,
+    key: &Key,
+    iv: Iv,
+) -> Result<Ciphertext, CryptoError> {
+    aes128_cbc_encrypt_with_padding(plaintext, key, iv, Padding::Pkcs7)
 }
 
+pub fn aes128_cbc_encrypt_with_padding(
+    plaintext: &Plaintext,
+    key: &Key,
+    iv: Iv,
+    padding: Padding,
+) -> Result<Ciphertext, CryptoError> {
+    cbc_encrypt::<Aes128>(plaintext, key, &iv, padding)
+}
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::data::AesKey;
+    use crate::set1::challenge1::{base64_decode_homebrew, Base64Config};
+    use crate::set2::challenge9::pkcs7_pad;
     use std::str::FromStr;
 
-
     #[test]
     fn test_aes128_cbc_decrypt() {
-        let ciphertext: String = include_str!("10.txt")
-            .chars()
-            .filter(|c| *c != '\n')
-            .collect();
-        let ciphertext = Ciphertext(base64::decode(ciphertext).unwrap());
-        let key = Key::from_str("YELLOW SUBMARINE").unwrap();
-        let iv = Iv(vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let ciphertext =
+            Ciphertext(base64_decode_homebrew(include_str!("10.txt"), Base64Config::MIME).unwrap());
+        let key = AesKey::aes128(b"YELLOW SUBMARINE").unwrap();
+        let iv = Iv::block(&[0; 16]).unwrap();
         let plaintext = aes128_cbc_decrypt(&ciphertext, &key, iv).unwrap();
-        let plaintext = dbg!(String::from_utf8(plaintext.0).unwrap());
+        let plaintext = String::from_utf8(plaintext.0).unwrap();
         assert!(plaintext.contains("Play that funky music, white boy"));
     }
 
     #[test]
     fn test_aes128_cbc_encrypt() {
-        let key = Key::from_str("0123456789abcdef").unwrap();
-        let iv = Iv(vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let key = AesKey::from_str("0123456789abcdef").unwrap();
+        let iv = Iv::block(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
         let plaintext = Plaintext::from_str("This is my testing plaintext which is a very plain text but also a plaintext which is used to test my code.").unwrap();
         let ciphertext = aes128_cbc_encrypt(&plaintext, &key, iv).unwrap();
-        let iv = Iv(vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let iv = Iv::block(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
         let new_plaintext = aes128_cbc_decrypt(&ciphertext, &key, iv).unwrap();
-        assert_eq!(pkcs7_pad(&plaintext, 16).0, new_plaintext.0);
+        assert_eq!(plaintext.0, new_plaintext.0);
+    }
+
+    #[test]
+    fn test_aes128_cbc_decrypt_without_padding() {
+        let key = AesKey::from_str("0123456789abcdef").unwrap();
+        let iv = Iv::block(&[0; 16]).unwrap();
+        let plaintext = Plaintext::from_str("YELLOW SUBMARINE").unwrap();
+        let ciphertext = aes128_cbc_encrypt(&plaintext, &key, iv).unwrap();
+        assert_eq!(32, ciphertext.0.len());
+        let iv = Iv::block(&[0; 16]).unwrap();
+        let new_plaintext =
+            aes128_cbc_decrypt_with_padding(&ciphertext, &key, iv, Padding::None).unwrap();
+        assert_eq!(pkcs7_pad(&plaintext, 16).unwrap().0, new_plaintext.0);
+    }
+
+    #[test]
+    fn test_aes128_cbc_wrong_iv_length() {
+        let key = AesKey::from_str("0123456789abcdef").unwrap();
+        let plaintext = Plaintext::from_str("YELLOW SUBMARINE").unwrap();
+        assert_eq!(
+            CryptoError::InvalidIvLength {
+                expected: 16,
+                actual: 8
+            },
+            aes128_cbc_encrypt(&plaintext, &key, Iv::try_from(vec![0; 8]).unwrap()).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_aes128_cbc_decrypt_bad_padding() {
+        let key = AesKey::from_str("0123456789abcdef").unwrap();
+        let iv = Iv::block(&[0; 16]).unwrap();
+        let plaintext = Plaintext::from_str("YELLOW SUBMARINE").unwrap();
+        let ciphertext =
+            aes128_cbc_encrypt_with_padding(&plaintext, &key, iv, Padding::None).unwrap();
+        let iv = Iv::block(&[0; 16]).unwrap();
+        let error = aes128_cbc_decrypt(&ciphertext, &key, iv).unwrap_err();
+        assert!(matches!(error, CryptoError::Padding(_)));
     }
 }
@@ -1,11 +1,140 @@
 use crate::data::Plaintext;
+use crate::error::CryptoError;
+use std::fmt;
 
-pub fn pkcs7_pad(input: &Plaintext, block_size: usize) -> Plaintext {
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Padding {
+    Pkcs7,
+    None,
+}
+
+impl std::str::FromStr for Padding {
+    type Err = CryptoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pkcs7" => Ok(Padding::Pkcs7),
+            "none" => Ok(Padding::None),
+            _ => Err(CryptoError::InvalidParameter(
+                "Unknown padding, expected pkcs7 or none",
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PaddingError {
+    InvalidBlockSize(usize),
+    NotBlockAligned {
+        len: usize,
+        block_size: usize,
+    },
+    ZeroPaddingLength,
+    PaddingTooLong {
+        padding_len: usize,
+        block_size: usize,
+    },
+    InvalidPaddingByte {
+        offset: usize,
+        value: u8,
+        expected: u8,
+    },
+}
+
+impl fmt::Display for PaddingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaddingError::InvalidBlockSize(block_size) => {
+                write!(
+                    f,
+                    "Invalid PKCS#7 block size {} (must be 1..=255)",
+                    block_size
+                )
+            }
+            PaddingError::NotBlockAligned { len, block_size } => write!(
+                f,
+                "Padded data of {} bytes is not a multiple of the block size {}",
+                len, block_size
+            ),
+            PaddingError::ZeroPaddingLength => write!(f, "Padding length is zero"),
+            PaddingError::PaddingTooLong {
+                padding_len,
+                block_size,
+            } => write!(
+                f,
+                "Padding length {} is longer than the block size {}",
+                padding_len, block_size
+            ),
+            PaddingError::InvalidPaddingByte {
+                offset,
+                value,
+                expected,
+            } => write!(
+                f,
+                "Invalid padding byte {:#04x} at offset {}, expected {:#04x}",
+                value, offset, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PaddingError {}
+
+/// Pads `input` as described in RFC 5652 section 6.3. Between 1 and `block_size` bytes are always
+/// added, so block aligned input gets a full block of padding.
+pub fn pkcs7_pad(input: &Plaintext, block_size: usize) -> Result<Plaintext, CryptoError> {
+    check_block_size(block_size)?;
     let mut data = input.0.clone();
-    let number_of_blocks = (data.len() + block_size - 1) / block_size;
-    let bytes_to_add = number_of_blocks * block_size - data.len();
+    let bytes_to_add = block_size - data.len() % block_size;
     data.resize(data.len() + bytes_to_add, bytes_to_add as u8);
-    Plaintext(data)
+    Ok(Plaintext(data))
+}
+
+/// Removes and validates padding added by `pkcs7_pad`.
+pub fn pkcs7_unpad(input: &Plaintext, block_size: usize) -> Result<Plaintext, CryptoError> {
+    Ok(unpad(input, block_size)?)
+}
+
+fn unpad(input: &Plaintext, block_size: usize) -> Result<Plaintext, PaddingError> {
+    check_block_size(block_size)?;
+    let data = &input.0;
+    if data.is_empty() || !data.len().is_multiple_of(block_size) {
+        return Err(PaddingError::NotBlockAligned {
+            len: data.len(),
+            block_size,
+        });
+    }
+    let padding_len = data[data.len() - 1];
+    if padding_len == 0 {
+        return Err(PaddingError::ZeroPaddingLength);
+    }
+    if padding_len as usize > block_size {
+        return Err(PaddingError::PaddingTooLong {
+            padding_len: padding_len as usize,
+            block_size,
+        });
+    }
+    let unpadded_len = data.len() - padding_len as usize;
+    if let Some((offset, value)) = data[unpadded_len..]
+        .iter()
+        .enumerate()
+        .find(|(_offset, value)| **value != padding_len)
+    {
+        return Err(PaddingError::InvalidPaddingByte {
+            offset: unpadded_len + offset,
+            value: *value,
+            expected: padding_len,
+        });
+    }
+    Ok(Plaintext(data[..unpadded_len].to_vec()))
+}
+
+fn check_block_size(block_size: usize) -> Result<(), PaddingError> {
+    if (1..=255).contains(&block_size) {
+        Ok(())
+    } else {
+        Err(PaddingError::InvalidBlockSize(block_size))
+    }
 }
 
 #[cfg(test)]
@@ -17,7 +146,7 @@ mod test {
     fn test_pkcs7_pad_1blocks_of_20bytes_16bytes_input() {
         let plaintext = "YELLOW SUBMARINE";
         let plaintext = Plaintext::from_str(plaintext).unwrap();
-        let plaintext_padded = pkcs7_pad(&plaintext, 20);
+        let plaintext_padded = pkcs7_pad(&plaintext, 20).unwrap();
         assert_eq!(20, plaintext_padded.0.len());
         assert_eq!(plaintext.0, plaintext_padded.0[0..16]);
         assert_eq!(vec![4, 4, 4, 4], plaintext_padded.0[16..20]);
@@ -27,7 +156,7 @@ mod test {
     fn test_pkcs7_pad_2blocks_of_10bytes_16bytes_input() {
         let plaintext = "YELLOW SUBMARINE";
         let plaintext = Plaintext::from_str(plaintext).unwrap();
-        let plaintext_padded = pkcs7_pad(&plaintext, 10);
+        let plaintext_padded = pkcs7_pad(&plaintext, 10).unwrap();
         assert_eq!(20, plaintext_padded.0.len());
         assert_eq!(plaintext.0, plaintext_padded.0[0..16]);
         assert_eq!(vec![4, 4, 4, 4], plaintext_padded.0[16..20]);
@@ -37,7 +166,7 @@ mod test {
     fn test_pkcs7_pad_1block_of_20bytes_19bytes_input() {
         let plaintext = "YELLOW SUBMARINE123";
         let plaintext = Plaintext::from_str(plaintext).unwrap();
-        let plaintext_padded = pkcs7_pad(&plaintext, 20);
+        let plaintext_padded = pkcs7_pad(&plaintext, 20).unwrap();
         assert_eq!(20, plaintext_padded.0.len());
         assert_eq!(plaintext.0, plaintext_padded.0[0..19]);
         assert_eq!(vec![1], plaintext_padded.0[19..20]);
@@ -47,7 +176,7 @@ mod test {
     fn test_pkcs7_pad_2blocks_of_10bytes_19bytes_input() {
         let plaintext = "YELLOW SUBMARINE123";
         let plaintext = Plaintext::from_str(plaintext).unwrap();
-        let plaintext_padded = pkcs7_pad(&plaintext, 10);
+        let plaintext_padded = pkcs7_pad(&plaintext, 10).unwrap();
         assert_eq!(20, plaintext_padded.0.len());
         assert_eq!(plaintext.0, plaintext_padded.0[0..19]);
         assert_eq!(vec![1], plaintext_padded.0[19..20]);
@@ -57,26 +186,27 @@ mod test {
     fn test_pkcs7_pad_2blocks_of_11bytes_16bytes_input() {
         let plaintext = "YELLOW SUBMARINE";
         let plaintext = Plaintext::from_str(plaintext).unwrap();
-        let plaintext_padded = pkcs7_pad(&plaintext, 11);
+        let plaintext_padded = pkcs7_pad(&plaintext, 11).unwrap();
         assert_eq!(22, plaintext_padded.0.len());
         assert_eq!(plaintext.0, plaintext_padded.0[0..16]);
         assert_eq!(vec![6, 6, 6, 6, 6, 6], plaintext_padded.0[16..22]);
     }
 
     #[test]
-    fn test_pkcs7_pad_no_pad() {
+    fn test_pkcs7_pad_block_aligned_input() {
         let plaintext = "YELLOW SUBMARINE";
         let plaintext = Plaintext::from_str(plaintext).unwrap();
-        let plaintext_padded = pkcs7_pad(&plaintext, 16);
-        assert_eq!(16, plaintext_padded.0.len());
-        assert_eq!(plaintext.0, plaintext_padded.0);
+        let plaintext_padded = pkcs7_pad(&plaintext, 16).unwrap();
+        assert_eq!(32, plaintext_padded.0.len());
+        assert_eq!(plaintext.0, plaintext_padded.0[0..16]);
+        assert_eq!(vec![16; 16], plaintext_padded.0[16..32]);
     }
 
     #[test]
     fn test_pkcs7_pad_full_pad() {
         let plaintext = "YELLOW SUBMARINES";
         let plaintext = Plaintext::from_str(plaintext).unwrap();
-        let plaintext_padded = pkcs7_pad(&plaintext, 16);
+        let plaintext_padded = pkcs7_pad(&plaintext, 16).unwrap();
         assert_eq!(32, plaintext_padded.0.len());
         assert_eq!(plaintext.0, plaintext_padded.0[0..17]);
         assert_eq!(
@@ -84,4 +214,72 @@ mod test {
             plaintext_padded.0[17..32]
         );
     }
+
+    #[test]
+    fn test_pkcs7_pad_invalid_block_size() {
+        let plaintext = Plaintext::from_str("YELLOW SUBMARINE").unwrap();
+        assert_eq!(
+            CryptoError::Padding(PaddingError::InvalidBlockSize(0)),
+            pkcs7_pad(&plaintext, 0).unwrap_err()
+        );
+        assert_eq!(
+            CryptoError::Padding(PaddingError::InvalidBlockSize(256)),
+            pkcs7_pad(&plaintext, 256).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_pkcs7_unpad_roundtrip() {
+        for len in 0..40 {
+            let plaintext = Plaintext(vec![b'A'; len]);
+            let padded = pkcs7_pad(&plaintext, 16).unwrap();
+            assert_eq!(plaintext.0, pkcs7_unpad(&padded, 16).unwrap().0);
+        }
+    }
+
+    #[test]
+    fn test_pkcs7_unpad_invalid_padding_byte() {
+        let padded = Plaintext(b"ICE ICE BABY\x01\x02\x03\x04".to_vec());
+        assert_eq!(
+            CryptoError::Padding(PaddingError::InvalidPaddingByte {
+                offset: 12,
+                value: 1,
+                expected: 4
+            }),
+            pkcs7_unpad(&padded, 16).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_pkcs7_unpad_zero_padding_length() {
+        let padded = Plaintext(b"ICE ICE BABY\x04\x04\x04\x00".to_vec());
+        assert_eq!(
+            CryptoError::Padding(PaddingError::ZeroPaddingLength),
+            pkcs7_unpad(&padded, 16).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_pkcs7_unpad_padding_too_long() {
+        let padded = Plaintext(b"ICE ICE BABY\x11\x11\x11\x11".to_vec());
+        assert_eq!(
+            CryptoError::Padding(PaddingError::PaddingTooLong {
+                padding_len: 17,
+                block_size: 16
+            }),
+            pkcs7_unpad(&padded, 16).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_pkcs7_unpad_not_block_aligned() {
+        let padded = Plaintext(b"ICE ICE BABY\x04\x04\x04".to_vec());
+        assert_eq!(
+            CryptoError::Padding(PaddingError::NotBlockAligned {
+                len: 15,
+                block_size: 16
+            }),
+            pkcs7_unpad(&padded, 16).unwrap_err()
+        );
+    }
 }
@@ -0,0 +1,475 @@
+use std::ops::RangeInclusive;
+
+use crate::data::{Ciphertext, Key, Plaintext};
+use crate::error::CryptoError;
+use crate::language_model::LanguageModel;
+use crate::scoring::Scorer;
+use crate::set1::challenge6::transpose_blocks;
+
+/// Which byte becomes the key for the next byte in autokey XOR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feedback {
+    Plaintext,
+    Ciphertext,
+}
+
+/// XORs every byte with the previous plaintext or ciphertext byte, and the first with `seed`.
+pub fn autokey_encrypt(plaintext: &Plaintext, seed: u8, feedback: Feedback) -> Ciphertext {
+    let mut key = seed;
+    plaintext
+        .0
+        .iter()
+        .map(|p| {
+            let c = p ^ key;
+            key = match feedback {
+                Feedback::Plaintext => *p,
+                Feedback::Ciphertext => c,
+            };
+            c
+        })
+        .collect()
+}
+
+pub fn autokey_decrypt(ciphertext: &Ciphertext, seed: u8, feedback: Feedback) -> Plaintext {
+    let mut key = seed;
+    ciphertext
+        .0
+        .iter()
+        .map(|c| {
+            let p = c ^ key;
+            key = match feedback {
+                Feedback::Plaintext => p,
+                Feedback::Ciphertext => *c,
+            };
+            p
+        })
+        .collect()
+}
+
+pub fn break_autokey_xor(
+    ciphertext: &Ciphertext,
+    feedback: Feedback,
+) -> Result<(u8, Plaintext), CryptoError> {
+    break_autokey_xor_with(ciphertext, feedback, LanguageModel::english())
+}
+
+/// Tries every seed. With ciphertext feedback only the first byte depends on it.
+pub fn break_autokey_xor_with(
+    ciphertext: &Ciphertext,
+    feedback: Feedback,
+    scorer: &dyn Scorer,
+) -> Result<(u8, Plaintext), CryptoError> {
+    if ciphertext.is_empty() {
+        return Err(CryptoError::EmptyInput);
+    }
+    let seed = best(0..=255, scorer, |seed| {
+        autokey_decrypt(ciphertext, *seed, feedback).0
+    });
+    Ok((seed, autokey_decrypt(ciphertext, seed, feedback)))
+}
+
+/// A repeating key that is incremented by `step` for every byte, so byte `i` is XOR'ed with
+/// `key[i % key.len()] + step * i`, wrapping around.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncrementingKey {
+    pub key: Key,
+    pub step: u8,
+}
+
+impl IncrementingKey {
+    fn key_byte(&self, index: usize) -> u8 {
+        key_byte(self.key[index % self.key.len()], self.step, index)
+    }
+}
+
+fn key_byte(base: u8, step: u8, index: usize) -> u8 {
+    base.wrapping_add(step.wrapping_mul(index as u8))
+}
+
+pub fn incrementing_key_xor(data: &[u8], key: &IncrementingKey) -> Result<Vec<u8>, CryptoError> {
+    if key.key.is_empty() {
+        return Err(CryptoError::EmptyInput);
+    }
+    Ok(data
+        .iter()
+        .enumerate()
+        .map(|(index, byte)| byte ^ key.key_byte(index))
+        .collect())
+}
+
+pub fn incrementing_key_encrypt(
+    plaintext: &Plaintext,
+    key: &IncrementingKey,
+) -> Result<Ciphertext, CryptoError> {
+    Ok(Ciphertext(incrementing_key_xor(&plaintext.0, key)?))
+}
+
+pub fn incrementing_key_decrypt(
+    ciphertext: &Ciphertext,
+    key: &IncrementingKey,
+) -> Result<Plaintext, CryptoError> {
+    Ok(Plaintext(incrementing_key_xor(&ciphertext.0, key)?))
+}
+
+pub struct IncrementingKeyXorOptions<'a> {
+    pub key_sizes: RangeInclusive<usize>,
+    /// Each step multiplies the work, so only search the steps that are expected.
+    pub steps: RangeInclusive<u8>,
+    /// Rates the transposed columns, so it only sees every key size'th byte.
+    pub column_scorer: &'a dyn Scorer,
+    /// Rates the complete plaintexts to pick the key size and step.
+    pub plaintext_scorer: &'a dyn Scorer,
+}
+
+impl Default for IncrementingKeyXorOptions<'static> {
+    fn default() -> Self {
+        IncrementingKeyXorOptions {
+            key_sizes: 1..=16,
+            steps: 1..=1,
+            column_scorer: LanguageModel::english_unigrams(),
+            plaintext_scorer: LanguageModel::english(),
+        }
+    }
+}
+
+/// Breaks every column of the ciphertext on its own for each key size and step, like
+/// `break_repeating_key_xor`. When several key sizes give the same plaintext the shortest wins.
+pub fn break_incrementing_key_xor(
+    ciphertext: &Ciphertext,
+    options: &IncrementingKeyXorOptions,
+) -> Result<(IncrementingKey, Plaintext), CryptoError> {
+    if ciphertext.is_empty() {
+        return Err(CryptoError::EmptyInput);
+    }
+    let mut best: Option<(f64, IncrementingKey)> = None;
+    for key_size in options.key_sizes.clone().filter(|ks| *ks > 0) {
+        let columns = transpose_blocks(&ciphertext.0, key_size);
+        for step in options.steps.clone() {
+            let key: Key = columns
+                .iter()
+                .enumerate()
+                .map(|(column, bytes)| {
+                    self::best(0..=255, options.column_scorer, |base| {
+                        bytes
+                            .iter()
+                            .enumerate()
+                            .map(|(row, c)| c ^ key_byte(*base, step, column + row * key_size))
+                            .collect()
+                    })
+                })
+                .collect();
+            let key = IncrementingKey { key, step };
+            let plaintext = incrementing_key_xor(&ciphertext.0, &key)?;
+            let score = options.plaintext_scorer.score(&plaintext);
+            if best.as_ref().is_none_or(|(best, _)| score > *best) {
+                best = Some((score, key));
+            }
+        }
+    }
+    let (_score, key) = best.ok_or(CryptoError::InvalidParameter("No key sizes to try"))?;
+    let plaintext = incrementing_key_decrypt(ciphertext, &key)?;
+    Ok((key, plaintext))
+}
+
+/// A Fibonacci linear feedback shift register. Each step outputs the lowest bit of `state`,
+/// shifts it right and feeds the parity of the tapped bits in at the top. Keystream bytes are
+/// made from eight steps, lowest bit first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lfsr {
+    pub width: u32,
+    pub taps: u32,
+    pub state: u32,
+}
+
+/// Tap positions, counted from 1 at the output end, that give a maximal period for widths 2
+/// to 16.
+const MAXIMAL_TAPS: [&[u32]; 15] = [
+    &[2, 1],
+    &[3, 2],
+    &[4, 3],
+    &[5, 3],
+    &[6, 5],
+    &[7, 6],
+    &[8, 6, 5, 4],
+    &[9, 5],
+    &[10, 7],
+    &[11, 9],
+    &[12, 11, 10, 4],
+    &[13, 12, 11, 8],
+    &[14, 13, 12, 2],
+    &[15, 14],
+    &[16, 15, 13, 4],
+];
+
+impl Lfsr {
+    pub fn new(width: u32, taps: u32, state: u32) -> Result<Lfsr, CryptoError> {
+        if !(1..=32).contains(&width) {
+            return Err(CryptoError::InvalidParameter(
+                "The LFSR width must be between 1 and 32",
+            ));
+        }
+        let mask = width_mask(width);
+        if state & mask == 0 {
+            return Err(CryptoError::InvalidParameter(
+                "The LFSR state must not be zero",
+            ));
+        }
+        Ok(Lfsr {
+            width,
+            taps: taps & mask,
+            state: state & mask,
+        })
+    }
+
+    /// Taps giving a period of 2^width - 1, for widths 2 to 16.
+    pub fn maximal_taps(width: u32) -> Option<u32> {
+        let positions = MAXIMAL_TAPS.get(width.checked_sub(2)? as usize)?;
+        Some(
+            positions
+                .iter()
+                .map(|position| 1 << (width - position))
+                .fold(0, |taps, tap| taps | tap),
+        )
+    }
+
+    fn next_bit(&mut self) -> u8 {
+        let output = (self.state & 1) as u8;
+        let feedback = (self.state & self.taps).count_ones() & 1;
+        self.state = (self.state >> 1) | (feedback << (self.width - 1));
+        output
+    }
+
+    pub fn next_byte(&mut self) -> u8 {
+        (0..8).fold(0, |byte, bit| byte | self.next_bit() << bit)
+    }
+}
+
+/// XORs `data` with the keystream of `lfsr`, leaving `lfsr` where the keystream ended.
+pub fn lfsr_xor(data: &[u8], lfsr: &mut Lfsr) -> Vec<u8> {
+    data.iter().map(|byte| byte ^ lfsr.next_byte()).collect()
+}
+
+pub fn lfsr_encrypt(plaintext: &Plaintext, lfsr: Lfsr) -> Ciphertext {
+    Ciphertext(lfsr_xor(&plaintext.0, &mut lfsr.clone()))
+}
+
+pub fn lfsr_decrypt(ciphertext: &Ciphertext, lfsr: Lfsr) -> Plaintext {
+    Plaintext(lfsr_xor(&ciphertext.0, &mut lfsr.clone()))
+}
+
+/// The widest register `break_lfsr_xor` will search every state of. Each state costs one
+/// language model score of a short sample, so a register this wide takes 2^24, about 16.7
+/// million, scores.
+pub const MAX_BRUTE_FORCE_LFSR_WIDTH: u32 = 24;
+
+/// How much of the ciphertext is decrypted to rate each state.
+const LFSR_SAMPLE_LEN: usize = 48;
+
+pub fn break_lfsr_xor(
+    ciphertext: &Ciphertext,
+    width: u32,
+    taps: u32,
+) -> Result<(Lfsr, Plaintext), CryptoError> {
+    break_lfsr_xor_with(ciphertext, width, taps, LanguageModel::english())
+}
+
+/// Tries every initial state of a register with known width and taps, see
+/// `Lfsr::maximal_taps`. That is 2^width - 1 scores of a 48 byte sample.
+pub fn break_lfsr_xor_with(
+    ciphertext: &Ciphertext,
+    width: u32,
+    taps: u32,
+    scorer: &dyn Scorer,
+) -> Result<(Lfsr, Plaintext), CryptoError> {
+    if width > MAX_BRUTE_FORCE_LFSR_WIDTH {
+        return Err(CryptoError::InvalidParameter(
+            "The LFSR is too wide to try every state",
+        ));
+    }
+    if ciphertext.is_empty() {
+        return Err(CryptoError::EmptyInput);
+    }
+    Lfsr::new(width, taps, 1)?;
+    let sample = &ciphertext[..ciphertext.len().min(LFSR_SAMPLE_LEN)];
+    let state = best(1..=width_mask(width), scorer, |state| {
+        lfsr_xor(sample, &mut Lfsr::new(width, taps, *state).unwrap())
+    });
+    let lfsr = Lfsr::new(width, taps, state)?;
+    Ok((lfsr, lfsr_decrypt(ciphertext, lfsr)))
+}
+
+pub fn break_lfsr_xor_unknown_taps(
+    ciphertext: &Ciphertext,
+    widths: RangeInclusive<u32>,
+) -> Result<(Lfsr, Plaintext), CryptoError> {
+    break_lfsr_xor_unknown_taps_with(ciphertext, widths, LanguageModel::english())
+}
+
+/// Like `break_lfsr_xor_with` for a register of unknown width and taps. Every width in
+/// `widths` is tried with its `Lfsr::maximal_taps`, so only registers with maximal period are
+/// found and the widths must be between 2 and 16. Each width w costs 2^w - 1 scores, about 131
+/// thousand for all of 2..=16.
+pub fn break_lfsr_xor_unknown_taps_with(
+    ciphertext: &Ciphertext,
+    widths: RangeInclusive<u32>,
+    scorer: &dyn Scorer,
+) -> Result<(Lfsr, Plaintext), CryptoError> {
+    if widths.is_empty() {
+        return Err(CryptoError::InvalidParameter("No widths to try"));
+    }
+    if ciphertext.is_empty() {
+        return Err(CryptoError::EmptyInput);
+    }
+    let registers = widths
+        .map(|width| {
+            let taps = Lfsr::maximal_taps(width).ok_or(CryptoError::InvalidParameter(
+                "Maximal taps are only known for widths 2 to 16",
+            ))?;
+            Ok((width, taps))
+        })
+        .collect::<Result<Vec<_>, CryptoError>>()?;
+    let sample = &ciphertext[..ciphertext.len().min(LFSR_SAMPLE_LEN)];
+    let candidates = registers.into_iter().flat_map(|(width, taps)| {
+        (1..=width_mask(width)).map(move |state| Lfsr::new(width, taps, state).unwrap())
+    });
+    let lfsr = best(candidates, scorer, |lfsr| {
+        lfsr_xor(sample, &mut lfsr.clone())
+    });
+    Ok((lfsr, lfsr_decrypt(ciphertext, lfsr)))
+}
+
+fn width_mask(width: u32) -> u32 {
+    u32::MAX >> (32 - width)
+}
+
+/// The candidate whose decryption scores best. Ties go to the first.
+fn best<T>(
+    candidates: impl Iterator<Item = T>,
+    scorer: &dyn Scorer,
+    decrypt: impl Fn(&T) -> Vec<u8>,
+) -> T {
+    let mut best: Option<(f64, T)> = None;
+    for candidate in candidates {
+        let score = scorer.score(&decrypt(&candidate));
+        if best.as_ref().is_none_or(|(best, _)| score > *best) {
+            best = Some((score, candidate));
+        }
+    }
+    best.expect("there are candidates").1
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    const TEXT: &str = "I'm back and I'm ringin' the bell \
+        A rockin' on the mike while the fly girls yell \
+        In ecstasy in the back of me \
+        Well that's my DJ Deshay cuttin' all them Z's";
+
+    #[test]
+    fn test_autokey() {
+        let plaintext = Plaintext::from_str(TEXT).unwrap();
+        for feedback in [Feedback::Plaintext, Feedback::Ciphertext] {
+            let ciphertext = autokey_encrypt(&plaintext, 0x5c, feedback);
+            assert_eq!(plaintext, autokey_decrypt(&ciphertext, 0x5c, feedback));
+        }
+
+        let ciphertext = autokey_encrypt(&plaintext, 0x5c, Feedback::Plaintext);
+        let (seed, broken) = break_autokey_xor(&ciphertext, Feedback::Plaintext).unwrap();
+        assert_eq!(0x5c, seed);
+        assert_eq!(plaintext, broken);
+
+        // Only the first byte depends on the seed, so it is a guess.
+        let ciphertext = autokey_encrypt(&plaintext, 0x5c, Feedback::Ciphertext);
+        let (_seed, broken) = break_autokey_xor(&ciphertext, Feedback::Ciphertext).unwrap();
+        assert_eq!(plaintext[1..], broken[1..]);
+        assert!(break_autokey_xor(&Ciphertext(vec![]), Feedback::Plaintext).is_err());
+    }
+
+    #[test]
+    fn test_incrementing_key() {
+        let plaintext = Plaintext::from_str(TEXT).unwrap();
+        let key = IncrementingKey {
+            key: Key::from_str("VIP").unwrap(),
+            step: 1,
+        };
+        let ciphertext = incrementing_key_encrypt(&plaintext, &key).unwrap();
+        assert_eq!(ciphertext[4], plaintext[4] ^ (b'I' + 4));
+        assert_eq!(
+            plaintext,
+            incrementing_key_decrypt(&ciphertext, &key).unwrap()
+        );
+        let options = IncrementingKeyXorOptions {
+            key_sizes: 1..=8,
+            ..IncrementingKeyXorOptions::default()
+        };
+        let (broken_key, broken) = break_incrementing_key_xor(&ciphertext, &options).unwrap();
+        assert_eq!(key, broken_key);
+        assert_eq!(plaintext, broken);
+    }
+
+    #[test]
+    fn test_break_incrementing_key_with_unknown_step() {
+        let plaintext = Plaintext::from_str(TEXT).unwrap();
+        let key = IncrementingKey {
+            key: Key::from_str("k").unwrap(),
+            step: 7,
+        };
+        let ciphertext = incrementing_key_encrypt(&plaintext, &key).unwrap();
+        let options = IncrementingKeyXorOptions {
+            key_sizes: 1..=2,
+            steps: 0..=255,
+            ..IncrementingKeyXorOptions::default()
+        };
+        let (broken_key, _) = break_incrementing_key_xor(&ciphertext, &options).unwrap();
+        assert_eq!(key, broken_key);
+    }
+
+    #[test]
+    fn test_maximal_taps() {
+        for width in 2..=16 {
+            let taps = Lfsr::maximal_taps(width).unwrap();
+            let mut lfsr = Lfsr::new(width, taps, 1).unwrap();
+            let mut period = 0u32;
+            loop {
+                lfsr.next_bit();
+                period += 1;
+                if lfsr.state == 1 {
+                    break;
+                }
+            }
+            assert_eq!((1 << width) - 1, period, "width {}", width);
+        }
+        assert_eq!(None, Lfsr::maximal_taps(17));
+    }
+
+    #[test]
+    fn test_lfsr() {
+        let plaintext = Plaintext::from_str(TEXT).unwrap();
+        let lfsr = Lfsr::new(12, Lfsr::maximal_taps(12).unwrap(), 0x9a5).unwrap();
+        let ciphertext = lfsr_encrypt(&plaintext, lfsr);
+        assert_eq!(plaintext, lfsr_decrypt(&ciphertext, lfsr));
+        let (broken_lfsr, broken) = break_lfsr_xor(&ciphertext, 12, lfsr.taps).unwrap();
+        assert_eq!(lfsr, broken_lfsr);
+        assert_eq!(plaintext, broken);
+        assert!(Lfsr::new(12, lfsr.taps, 0x1000).is_err());
+        assert!(break_lfsr_xor(&ciphertext, 32, lfsr.taps).is_err());
+    }
+
+    #[test]
+    fn test_lfsr_unknown_taps() {
+        let plaintext = Plaintext::from_str(TEXT).unwrap();
+        let lfsr = Lfsr::new(11, Lfsr::maximal_taps(11).unwrap(), 0x3c7).unwrap();
+        let ciphertext = lfsr_encrypt(&plaintext, lfsr);
+        let (broken_lfsr, broken) = break_lfsr_xor_unknown_taps(&ciphertext, 2..=12).unwrap();
+        assert_eq!(lfsr, broken_lfsr);
+        assert_eq!(plaintext, broken);
+        assert!(break_lfsr_xor_unknown_taps(&ciphertext, 2..=17).is_err());
+        assert!(break_lfsr_xor_unknown_taps(&ciphertext, 1..=4).is_err());
+        #[allow(clippy::reversed_empty_ranges)]
+        let none = 5..=4;
+        assert!(break_lfsr_xor_unknown_taps(&ciphertext, none).is_err());
+    }
+}